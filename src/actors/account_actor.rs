@@ -1,42 +1,315 @@
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, SystemTime};
 
 use tokio::spawn;
-use tokio::sync::mpsc;
-use tracing::{debug, warn};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
 
-use crate::models::{Account, Transaction};
-use crate::storage::Storage;
+use crate::actors::{Envelope, Origin, TransferLeg};
+use crate::audit::{AuditEntry, AuditTrail};
+use crate::engine::{Claim, Clock, SystemClock};
+use crate::models::{Account, AccountError, AccountEvent, AccountPolicy, IdempotencyScope, Transaction};
+use crate::rejects::{Rejection, RejectsSender};
+use crate::report::RunReport;
+use crate::storage::{AsyncStorage, StorageError};
 use crate::types::AccountId;
 
+/// Shared resources handed to every actor spawned by the engine.
+pub struct ActorContext<S: AsyncStorage> {
+    pub storage: Arc<S>,
+    pub rejects: Option<RejectsSender>,
+    pub events: Option<mpsc::UnboundedSender<Vec<AccountEvent>>>,
+    pub audit: Option<Arc<AuditTrail>>,
+    pub report: Arc<Mutex<RunReport>>,
+    pub policy: AccountPolicy,
+    pub clock: Arc<dyn Clock>,
+    pub storage_attempts: u32,
+    pub storage_backoff: Duration,
+    pub failure: Arc<OnceLock<StorageError>>
+}
+
+impl<S: AsyncStorage> ActorContext<S> {
+    pub fn new(storage: Arc<S>) -> Self {
+        Self {
+            storage,
+            rejects: None,
+            events: None,
+            audit: None,
+            report: Arc::new(Mutex::new(RunReport::default())),
+            policy: AccountPolicy::default(),
+            clock: Arc::new(SystemClock),
+            storage_attempts: 5,
+            storage_backoff: Duration::from_millis(100),
+            failure: Arc::new(OnceLock::new())
+        }
+    }
+
+    pub fn with_policy(mut self, policy: AccountPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_rejects(mut self, rejects: Option<RejectsSender>) -> Self {
+        self.rejects = rejects;
+        self
+    }
+
+    pub fn with_events(mut self, events: Option<mpsc::UnboundedSender<Vec<AccountEvent>>>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_audit(mut self, audit: Option<Arc<AuditTrail>>) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    pub fn with_storage_retries(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.storage_attempts = attempts.max(1);
+        self.storage_backoff = backoff;
+        self
+    }
+
+    pub fn with_failure(mut self, failure: Arc<OnceLock<StorageError>>) -> Self {
+        self.failure = failure;
+        self
+    }
+}
+
+impl<S: AsyncStorage> Clone for ActorContext<S> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            rejects: self.rejects.clone(),
+            events: self.events.clone(),
+            audit: self.audit.clone(),
+            report: self.report.clone(),
+            policy: self.policy,
+            clock: self.clock.clone(),
+            storage_attempts: self.storage_attempts,
+            storage_backoff: self.storage_backoff,
+            failure: self.failure.clone()
+        }
+    }
+}
+
 pub struct AccountActor;
 
 impl AccountActor {
     /// Spawns a new actor and returns its input channel.
-    pub fn spawn<S: Storage>(account_id: AccountId, storage: Arc<S>, guard_sender: mpsc::Sender<()>) -> mpsc::UnboundedSender<Transaction> {
-        let (sender, mut receiver) = mpsc::unbounded_channel();
+    pub fn spawn<S: AsyncStorage>(account_id: AccountId, context: ActorContext<S>, guard_sender: mpsc::Sender<()>) -> mpsc::UnboundedSender<Envelope> {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Envelope>();
         
         spawn(async move {
-            let mut account = storage.load(account_id)
-                .unwrap_or_else(|| Account::new(account_id));
+            //NOTE: An actor that cannot load its state must not process anything, or it would overwrite the stored state on save
+            let Some(loaded) = Self::with_retries(&context, account_id, "load", || context.storage.load(account_id)).await else {
+                return;
+            };
+
+            let mut account = loaded.unwrap_or_else(|| Account::new(account_id));
+
+            let was_locked = account.is_locked();
+            let mut report = RunReport::default();
+
+            while let Some(Envelope { transaction, origin, leg, claim, .. }) = receiver.recv().await {
+                let now = context.clock.now();
+                Self::expire_holds(&mut account, now, &context, &mut report);
+
+                let result = match leg {
+                    Some(TransferLeg::Debit { prepared, credited }) => Self::debit(&mut account, &transaction, claim, now, &context, prepared, credited).await,
+                    Some(TransferLeg::Credit { prepared, credited }) => {
+                        Self::credit(&mut account, &transaction, origin.as_ref(), now, &context, prepared, credited).await;
+                        continue;
+                    }
+                    None => Self::apply(&mut account, &transaction, claim, now, &context)
+                };
 
-            while let Some(transaction) = receiver.recv().await {
-                match account.apply(&transaction) {
-                    Ok(_) => {
+                if let Some(trail) = &context.audit && trail.account_id() == account_id {
+                    trail.record(AuditEntry::new(&transaction, origin.as_ref(), &account, result.as_ref().err()));
+                }
+
+                match result {
+                    Ok(events) => {
+                        report.record_applied(transaction.transaction_type);
                         //NOTE: If using Kafka in production you can consider commiting the message (transaction)
                         debug!("Transaction [{}]:[{:?}] for client [{}] processed", transaction.transaction_id, transaction.transaction_type, transaction.account_id);
+
+                        if let Some(log) = &context.events {
+                            let _ = log.send(events);
+                        }
                     },
                     Err(error) => {
                         //NOTE: None of the current errors are critical, if using Kafka in production you can consider commiting the message (transaction)
                         warn!("{error}");
+                        report.record_rejected(transaction.transaction_type, &error);
+
+                        if let Some(rejects) = &context.rejects {
+                            rejects.send(Rejection::account(&transaction, origin.as_ref(), &error));
+                        }
                     }
                 }
             }
 
-            storage.save(account_id, account);
+            Self::expire_holds(&mut account, context.clock.now(), &context, &mut report);
+
+            if account.is_locked() && !was_locked {
+                report.accounts_locked += 1;
+            }
+
+            Self::with_retries(&context, account_id, "save", || context.storage.save(account_id, account.clone())).await;
+            context.report.lock().unwrap_or_else(PoisonError::into_inner).merge(report);
 
             drop(guard_sender);
         });
         
         sender
     }
-}
\ No newline at end of file
+
+    /// Runs a storage operation, retrying failures with an exponential backoff.
+    ///
+    /// Once every attempt has failed the error is recorded as the engine's fatal failure and `None` is returned.
+    async fn with_retries<S, T, F, O>(context: &ActorContext<S>, account_id: AccountId, operation: &str, mut attempt: F) -> Option<T>
+    where
+        S: AsyncStorage,
+        F: FnMut() -> O,
+        O: Future<Output = Result<T, StorageError>>
+    {
+        let mut backoff = context.storage_backoff;
+
+        for number in 1..=context.storage_attempts {
+            match attempt().await {
+                Ok(value) => return Some(value),
+                Err(storage_error) if number < context.storage_attempts => {
+                    warn!("Attempt [{number}] to {operation} client [{account_id}] failed, retrying in {backoff:?}: {storage_error}");
+                    sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                Err(storage_error) => {
+                    error!("Failed to {operation} client [{account_id}] after [{number}] attempts: {storage_error}");
+                    let _ = context.failure.set(storage_error);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Releases the expired authorization holds of the account, logging their events like those of a transaction.
+    fn expire_holds<S: AsyncStorage>(account: &mut Account, now: SystemTime, context: &ActorContext<S>, report: &mut RunReport) {
+        let events = account.expire_holds(now);
+
+        if events.is_empty() {
+            return;
+        }
+
+        debug!("Expired [{}] authorization holds of client [{}]", events.len(), account.account_id());
+        report.holds_expired += events.len() as u64;
+
+        if let Some(log) = &context.events {
+            let _ = log.send(events);
+        }
+    }
+
+    /// Applies the transaction according to the ownership of its ID claimed by the engine.
+    ///
+    /// Amount-bearing transactions are rejected when the engine found their ID owned by another client
+    /// under global idempotency. References to an ID owned by another client are reported as a mismatch.
+    fn apply<S: AsyncStorage>(account: &mut Account, transaction: &Transaction, claim: Option<Claim>, now: SystemTime, context: &ActorContext<S>) -> Result<Vec<AccountEvent>, AccountError> {
+        if !transaction.transaction_type.is_amount_bearing() {
+            return account.apply_at(transaction, &context.policy, now).map_err(|error| match error {
+                AccountError::TransactionNotFound { .. } => match claim {
+                    Some(Claim::Foreign(owner_id)) => AccountError::client_mismatch(transaction, owner_id),
+                    _ => error
+                },
+                _ => error
+            })
+        }
+
+        Self::check_claim(transaction, claim, context)?;
+
+        account.apply_at(transaction, &context.policy, now)
+    }
+
+    /// Applies the debit side of a transfer, once the destination applied the credit side.
+    ///
+    /// The actor waits for the destination in between, so nothing else can change the account
+    /// after the debit was checked. The transfer is reported, applied or rejected, by this side only.
+    async fn debit<S: AsyncStorage>(
+        account: &mut Account,
+        transaction: &Transaction,
+        claim: Option<Claim>,
+        now: SystemTime,
+        context: &ActorContext<S>,
+        prepared: oneshot::Sender<bool>,
+        credited: oneshot::Receiver<Result<(), AccountError>>
+    ) -> Result<Vec<AccountEvent>, AccountError> {
+        Self::check_claim(transaction, claim, context)?;
+        let decided = account.decide(transaction, &context.policy, Some(now));
+
+        let _ = prepared.send(decided.is_ok());
+
+        match decided {
+            Ok(events) => match credited.await {
+                Ok(Ok(())) => {
+                    account.commit(&events);
+                    Ok(events)
+                }
+                Ok(Err(error)) => Err(error),
+                //NOTE: Legs are only dispatched for transfers with a destination, the destination actor is gone when it could not load its state
+                Err(_) => Err(AccountError::destination_unavailable(transaction, transaction.destination_id.unwrap_or_default()))
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    /// Applies the credit side of a transfer once the source checked the debit, and reports the outcome back to it.
+    async fn credit<S: AsyncStorage>(
+        account: &mut Account,
+        transaction: &Transaction,
+        origin: Option<&Origin>,
+        now: SystemTime,
+        context: &ActorContext<S>,
+        prepared: oneshot::Receiver<bool>,
+        credited: oneshot::Sender<Result<(), AccountError>>
+    ) {
+        //NOTE: The source drops its side unanswered when it rejects the transfer early or cannot load its state
+        if !prepared.await.unwrap_or(false) {
+            return;
+        }
+
+        let result = account.apply_at(transaction, &context.policy, now);
+
+        if let Some(trail) = &context.audit && trail.account_id() == account.account_id() {
+            trail.record(AuditEntry::new(transaction, origin, account, result.as_ref().err()));
+        }
+
+        let outcome = match result {
+            Ok(events) => {
+                if let Some(log) = &context.events {
+                    let _ = log.send(events);
+                }
+
+                Ok(())
+            }
+            Err(error) => Err(error)
+        };
+
+        let _ = credited.send(outcome);
+    }
+
+    /// Rejects an amount-bearing transaction whose ID the engine found owned by another client under global idempotency.
+    fn check_claim<S: AsyncStorage>(transaction: &Transaction, claim: Option<Claim>, context: &ActorContext<S>) -> Result<(), AccountError> {
+        if context.policy.idempotency == IdempotencyScope::Global && matches!(claim, Some(Claim::Foreign(_))) {
+            return Err(AccountError::duplicate_transaction(transaction))
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use crate::actors::TransferLeg;
+use crate::checkpoint::InputPosition;
+use crate::engine::Claim;
+use crate::models::Transaction;
+
+/// A transaction travelling through the pipeline together with where it came from.
+#[derive(Debug)]
+pub struct Envelope {
+    pub transaction: Transaction,
+    /// Present when the transaction was read from an input, absent for streamed transactions.
+    pub origin: Option<Origin>,
+    /// Where the inputs continue after this transaction, absent for streamed transactions.
+    pub position: Option<InputPosition>,
+    /// The side of a transfer the receiving actor applies, attached by the engine when it dispatches transfers.
+    pub leg: Option<TransferLeg>,
+    /// Who owns the transaction ID, claimed by the engine in input order before dispatching.
+    pub claim: Option<Claim>
+}
+
+/// The location of a transaction inside its input, used for reporting.
+#[derive(Debug, Clone)]
+pub struct Origin {
+    /// Display name of the input (file path or `<stdin>`).
+    pub input: Arc<str>,
+    /// The 1-based line number of the row inside the input.
+    pub line: u64,
+    /// The row as read from the input.
+    pub row: String
+}
+
+impl From<Transaction> for Envelope {
+    fn from(transaction: Transaction) -> Self {
+        Self {
+            transaction,
+            origin: None,
+            position: None,
+            leg: None,
+            claim: None
+        }
+    }
+}
@@ -1,5 +1,9 @@
 mod account_actor;
+mod envelope;
 #[cfg(test)]
 mod tests;
+mod transfer;
 
-pub use account_actor::AccountActor;
+pub use account_actor::{AccountActor, ActorContext};
+pub use envelope::{Envelope, Origin};
+pub use transfer::TransferLeg;
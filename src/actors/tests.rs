@@ -1,4 +1,4 @@
-use super::AccountActor;
+use super::{AccountActor, ActorContext, Envelope};
 
 use std::str::FromStr;
 use std::sync::Arc;
@@ -17,14 +17,16 @@ fn create_transaction(transaction_type: TransactionType, transaction_id: u32, ac
         transaction_type,
         transaction_id,
         account_id,
-        amount: Some(Decimal::from_str(amount)?)
+        amount: Some(Decimal::from_str(amount)?),
+        currency: None,
+        destination_id: None
     })
 }
 
 /// A test harness to simplify interacting with an AccountActor.
 /// Encapsulates the wiring of command and guard channels.
 struct ActorHarness {
-    sender: Option<mpsc::UnboundedSender<Transaction>>,
+    sender: Option<mpsc::UnboundedSender<Envelope>>,
     guard_sender: Option<mpsc::Sender<()>>,
     guard_receiver: Option<mpsc::Receiver<()>>
 }
@@ -32,7 +34,7 @@ struct ActorHarness {
 impl ActorHarness {
     fn new(account_id: AccountId, storage: Arc<AccountStorage>) -> Self {
         let (guard_sender, guard_receiver) = mpsc::channel(1);
-        let sender = AccountActor::spawn(account_id, storage, guard_sender.clone());
+        let sender = AccountActor::spawn(account_id, ActorContext::new(storage), guard_sender.clone());
 
         Self {
             sender: Some(sender),
@@ -43,7 +45,7 @@ impl ActorHarness {
 
     fn send(&self, transaction: Transaction) -> Result<()> {
         if let Some(sender) = &self.sender {
-            sender.send(transaction).map_err(|_| anyhow!("Failed to send transaction"))
+            sender.send(Envelope::from(transaction)).map_err(|_| anyhow!("Failed to send transaction"))
         } else {
             Err(anyhow!("Sender already dropped"))
         }
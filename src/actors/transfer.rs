@@ -0,0 +1,36 @@
+use tokio::sync::oneshot;
+
+use crate::models::AccountError;
+
+/// One side of a transfer, handed to the actors of both clients by the engine.
+///
+/// Both sides are dispatched in input order, so every actor reaches its side after whatever
+/// it received before the transfer. The source checks the debit first and tells the destination
+/// whether it can be made, then waits while the destination checks and applies the credit. The
+/// debit is only applied once the credit was, so either both sides apply or neither does.
+#[derive(Debug)]
+pub enum TransferLeg {
+    /// Held by the actor of the client debited by the transfer.
+    Debit {
+        prepared: oneshot::Sender<bool>,
+        credited: oneshot::Receiver<Result<(), AccountError>>
+    },
+    /// Held by the actor of the client credited by the transfer.
+    Credit {
+        prepared: oneshot::Receiver<bool>,
+        credited: oneshot::Sender<Result<(), AccountError>>
+    }
+}
+
+impl TransferLeg {
+    /// Creates the debit and credit sides of a single transfer.
+    pub fn pair() -> (Self, Self) {
+        let (prepared_sender, prepared_receiver) = oneshot::channel();
+        let (credited_sender, credited_receiver) = oneshot::channel();
+
+        let debit = Self::Debit { prepared: prepared_sender, credited: credited_receiver };
+        let credit = Self::Credit { prepared: prepared_receiver, credited: credited_sender };
+
+        (debit, credit)
+    }
+}
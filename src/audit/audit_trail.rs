@@ -0,0 +1,103 @@
+use std::sync::{Mutex, PoisonError};
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::actors::Origin;
+use crate::models::{Account, AccountError, Currency, Transaction, TransactionType};
+use crate::types::{AccountId, TransactionId};
+
+/// A single step in the history of a client: one applied or rejected transaction and the
+/// balance it acts on right after it.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct AuditEntry {
+    /// Display name of the input the transaction was read from.
+    pub input: Option<String>,
+    /// The 1-based line number of the transaction inside its input.
+    pub line: Option<u64>,
+    #[serde(rename = "client")]
+    pub account_id: AccountId,
+    #[serde(rename = "tx")]
+    pub transaction_id: TransactionId,
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    pub amount: Option<Decimal>,
+    /// The currency of the balance, the one of the referenced transaction for disputes, resolves and chargebacks.
+    pub currency: Option<Currency>,
+    /// Stable reason code of the rejection, absent when the transaction was applied.
+    pub reason: Option<&'static str>,
+    /// Funds available after the transaction.
+    pub available: Decimal,
+    /// Funds held after the transaction.
+    pub held: Decimal,
+    /// Total funds after the transaction.
+    pub total: Decimal,
+    /// Whether the account is locked after the transaction.
+    pub locked: bool
+}
+
+impl AuditEntry {
+    pub(crate) fn new(transaction: &Transaction, origin: Option<&Origin>, account: &Account, error: Option<&AccountError>) -> Self {
+        let currency = if transaction.transaction_type.is_amount_bearing() {
+            transaction.currency.clone()
+        } else {
+            account.ledger.get(&transaction.transaction_id).and_then(|entry| entry.currency.clone())
+        };
+
+        let balance = account.balance(currency.as_ref());
+
+        Self {
+            input: origin.map(|origin| origin.input.to_string()),
+            line: origin.map(|origin| origin.line),
+            account_id: account.account_id(),
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+            amount: transaction.amount,
+            currency,
+            reason: error.map(AccountError::code),
+            available: balance.available(),
+            held: balance.held(),
+            total: balance.total(),
+            locked: account.is_locked()
+        }
+    }
+
+    /// Whether the transaction was applied rather than rejected.
+    pub fn is_applied(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// Ordered history of a single client, recorded by the engine while it processes transactions.
+///
+/// Every transaction addressed to the client is recorded, applied or rejected, in the order the
+/// account saw it. Rows that could not be parsed are not attributed to any client and are left out.
+pub struct AuditTrail {
+    account_id: AccountId,
+    entries: Mutex<Vec<AuditEntry>>
+}
+
+impl AuditTrail {
+    /// Creates an empty trail for the client.
+    pub fn new(account_id: AccountId) -> Self {
+        Self {
+            account_id,
+            entries: Mutex::new(Vec::new())
+        }
+    }
+
+    /// The audited client.
+    pub fn account_id(&self) -> AccountId {
+        self.account_id
+    }
+
+    /// The steps recorded so far, oldest first.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    pub(crate) fn record(&self, entry: AuditEntry) {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).push(entry);
+    }
+}
\ No newline at end of file
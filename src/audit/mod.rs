@@ -0,0 +1,7 @@
+mod audit_trail;
+#[cfg(test)]
+mod tests;
+mod verification;
+
+pub use audit_trail::{AuditEntry, AuditTrail};
+pub use verification::Verification;
\ No newline at end of file
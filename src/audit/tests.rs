@@ -0,0 +1,59 @@
+use super::{AuditEntry, AuditTrail, Verification};
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+
+use crate::models::{Account, ChainError, Transaction};
+use crate::storage::{AccountStorage, Storage};
+
+#[test]
+fn test_audit_trail_records_outcomes_and_balances_in_order() -> Result<()> {
+    let trail = AuditTrail::new(1);
+    let mut account = Account::new(1);
+
+    for transaction in [
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::withdrawal(1, 2, Decimal::ONE_HUNDRED),
+        Transaction::dispute(1, 1)
+    ] {
+        let result = account.apply(&transaction);
+        trail.record(AuditEntry::new(&transaction, None, &account, result.as_ref().err()));
+    }
+
+    let entries = trail.entries();
+
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].is_applied());
+    assert_eq!(entries[0].available, Decimal::TEN);
+    assert_eq!(entries[1].reason, Some("E_INSUFFICIENT_FUNDS"));
+    assert_eq!(entries[1].total, Decimal::TEN);
+    assert_eq!(entries[2].held, Decimal::TEN);
+    assert!(entries[2].available.is_zero());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verification_reports_the_lowest_broken_client() -> Result<()> {
+    let storage = AccountStorage::new();
+
+    for account_id in 1..=4 {
+        let mut account = Account::new(account_id);
+        account.apply(&Transaction::deposit(account_id, account_id.into(), Decimal::TEN))?;
+
+        if account_id >= 3 {
+            account.held = Decimal::ONE;
+        }
+
+        storage.save(account_id, account);
+    }
+
+    let verification = Verification::run(&storage).await?;
+
+    assert_eq!(verification.verified, 2);
+    assert_eq!(verification.broken, 2);
+    assert!(!verification.is_intact());
+    assert_eq!(verification.first_broken, Some(ChainError::StateMismatch { account_id: 3 }));
+
+    Ok(())
+}
\ No newline at end of file
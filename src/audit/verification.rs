@@ -0,0 +1,51 @@
+use tokio::pin;
+use tokio_stream::StreamExt;
+
+use crate::models::ChainError;
+use crate::storage::{AsyncStorage, StorageError};
+
+/// Result of verifying the tamper-evident history of every stored account.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Verification {
+    /// Accounts whose history is intact.
+    pub verified: u64,
+    /// Accounts whose history is broken.
+    pub broken: u64,
+    /// The first broken link of the lowest broken client, if any.
+    pub first_broken: Option<ChainError>
+}
+
+impl Verification {
+    /// Walks the storage and verifies every account, see [`Account::verify`](crate::Account::verify).
+    ///
+    /// # Errors
+    /// Returns an error if the storage cannot be scanned, a broken history is reported in the result instead.
+    pub async fn run<S: AsyncStorage>(storage: &S) -> Result<Self, StorageError> {
+        let mut verification = Self::default();
+        let accounts = storage.scan();
+
+        pin!(accounts);
+
+        while let Some(account) = accounts.next().await {
+            match account?.verify() {
+                Ok(()) => verification.verified += 1,
+                Err(error) => {
+                    verification.broken += 1;
+
+                    //NOTE: Storage scans are unordered, keep the lowest client so the report is deterministic
+                    if verification.first_broken.as_ref().is_none_or(|first| error.account_id() < first.account_id()) {
+                        verification.first_broken = Some(error);
+                    }
+                }
+            }
+        }
+
+        Ok(verification)
+    }
+
+    /// Whether every stored history is intact.
+    pub fn is_intact(&self) -> bool {
+        self.broken == 0
+    }
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::InputPosition;
+use crate::models::Account;
+use crate::snapshot::{read_accounts_file, write_accounts_file};
+
+const FORMAT: &str = "async-transaction-engine/checkpoint";
+const VERSION: u32 = 2;
+
+/// A consistent view of a run at a row boundary: every account holds exactly the transactions
+/// read before `position`, and none after it.
+///
+/// Stored in the same checksummed layout as a [`Snapshot`](crate::Snapshot), with the inputs and
+/// position of the run in its header, and atomically renamed into place so a crash never leaves
+/// a torn checkpoint.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    inputs: Vec<String>,
+    position: InputPosition,
+    accounts: Vec<Account>
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    inputs: Vec<String>,
+    position: InputPosition,
+    accounts: usize
+}
+
+impl Checkpoint {
+    pub(crate) fn new(inputs: Vec<String>, position: InputPosition, accounts: Vec<Account>) -> Self {
+        Self { inputs, position, accounts }
+    }
+
+    /// Reads a checkpoint written by a previous run.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, was written by an unsupported version, or fails its checksum.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (header, accounts) = read_accounts_file::<Header>(path, |header| {
+            if header.format != FORMAT {
+                return Err(anyhow!("Not a checkpoint: {}", path.display()))
+            }
+
+            if header.version != VERSION {
+                return Err(anyhow!("Unsupported checkpoint version [{}] in: {}", header.version, path.display()))
+            }
+
+            Ok(())
+        })?;
+
+        if accounts.len() != header.accounts {
+            return Err(anyhow!("Checkpoint holds [{}] of [{}] accounts: {}", accounts.len(), header.accounts, path.display()))
+        }
+
+        Ok(Self {
+            inputs: header.inputs,
+            position: header.position,
+            accounts
+        })
+    }
+
+    /// The inputs of the checkpointed run, as given on the command line after resolution.
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    /// Where reading continues when resuming from this checkpoint.
+    pub fn position(&self) -> InputPosition {
+        self.position
+    }
+
+    /// The state of every account at the checkpoint.
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> Result<()> {
+        let header = Header {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            inputs: self.inputs.clone(),
+            position: self.position,
+            accounts: self.accounts.len()
+        };
+
+        write_accounts_file(path, &header, &self.accounts)
+    }
+}
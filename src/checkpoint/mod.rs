@@ -0,0 +1,20 @@
+mod checkpoint_file;
+#[cfg(test)]
+mod tests;
+
+use serde::{Deserialize, Serialize};
+
+pub use checkpoint_file::Checkpoint;
+
+/// Where the inputs of a run continue after a given row, used to resume an interrupted run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputPosition {
+    /// The 0-based index of the input in the resolved input list.
+    pub input: usize,
+    /// Byte offset of the next row inside the input.
+    pub byte: u64,
+    /// The 1-based line number of the next row inside the input.
+    pub line: u64,
+    /// The number of records (including the header) read from the input so far.
+    pub record: u64
+}
@@ -0,0 +1,56 @@
+use super::{Checkpoint, InputPosition};
+
+use std::fs::{read_to_string, write};
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use tempfile::tempdir;
+
+use crate::models::{Account, Transaction};
+
+#[test]
+fn test_checkpoint_round_trips_accounts_and_position() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("checkpoint.jsonl");
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::TEN))?;
+    account.apply(&Transaction::dispute(1, 1))?;
+
+    let position = InputPosition { input: 1, byte: 120, line: 5, record: 4 };
+    Checkpoint::new(vec!["a.csv".into(), "b.csv".into()], position, vec![account]).write(&path)?;
+
+    let checkpoint = Checkpoint::load(&path)?;
+    let mut restored = checkpoint.accounts()[0].clone();
+
+    assert_eq!(checkpoint.position(), position);
+    assert_eq!(checkpoint.inputs(), ["a.csv", "b.csv"]);
+    assert_eq!(restored.held(), Decimal::TEN);
+
+    // The ledger and disputes are part of the checkpoint, so the dispute can still be resolved
+    restored.apply(&Transaction::resolve(1, 1))?;
+
+    assert_eq!(restored.available(), Decimal::TEN);
+
+    Ok(())
+}
+
+#[test]
+fn test_checkpoint_rejects_unknown_versions_and_missing_accounts() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("checkpoint.jsonl");
+
+    Checkpoint::new(vec![], InputPosition::default(), vec![Account::new(1), Account::new(2)]).write(&path)?;
+
+    let contents = read_to_string(&path)?;
+    let truncated: Vec<&str> = contents.lines().take(2).collect();
+    write(&path, truncated.join("\n"))?;
+
+    assert!(Checkpoint::load(&path).is_err());
+
+    write(&path, contents.replace("\"version\":2", "\"version\":99"))?;
+
+    assert!(Checkpoint::load(&path).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use tracing::level_filters::LevelFilter;
+
+use async_transaction_engine::{AccountId, ExcessScale, IdempotencyScope, InputFormat, OutputFormat, RejectsFormat, RoundingMode, SortKey};
+
+/// Processes a series of transactions and writes the final state of every account, sorted by client, to STDOUT.
+#[derive(Debug, Parser)]
+#[command(name = "async-transaction-engine", version, about)]
+pub struct Cli {
+    /// CSV or JSON Lines inputs processed in order as one stream. Use `-` for STDIN, a directory to
+    /// process every supported file it contains sorted by name, or a quoted glob pattern sorted by path.
+    #[arg(required_unless_present = "verify", value_name = "INPUT")]
+    pub inputs: Vec<String>,
+
+    /// Format of every input: csv or jsonl. Inferred from each file extension by default, STDIN is CSV.
+    #[arg(long, value_name = "FORMAT")]
+    pub input_format: Option<InputFormat>,
+
+    /// Writes the account states (or the audit trail) to this file instead of STDOUT.
+    #[arg(short, long, value_name = "PATH", conflicts_with = "verify")]
+    pub output: Option<PathBuf>,
+
+    /// Format of the account states: csv, json or jsonl. Inferred from the `--output` extension by
+    /// default, CSV on STDOUT.
+    #[arg(long, value_name = "FORMAT", conflicts_with_all = ["audit", "verify"])]
+    pub output_format: Option<OutputFormat>,
+
+    /// Column the account states are sorted by: client, available, held, total or locked. Ties are
+    /// broken by client.
+    #[arg(long, value_name = "KEY", default_value = "client", conflicts_with_all = ["audit", "verify"])]
+    pub sort: SortKey,
+
+    /// Logging verbosity written to STDERR: error, warn, info, debug or trace.
+    #[arg(short, long, value_name = "LEVEL", default_value = "error")]
+    pub log_level: LevelFilter,
+
+    /// Persists account state in this directory (write-ahead log plus snapshot), continuing from
+    /// the state left by previous runs. Without it, state is kept in memory for the run only.
+    #[arg(long, value_name = "DIR", conflicts_with = "resume")]
+    pub state: Option<PathBuf>,
+
+    /// Persists account state in this SQLite database, continuing from the state left by
+    /// previous runs.
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["state", "resume"])]
+    pub sqlite: Option<PathBuf>,
+
+    /// Seeds the storage with a snapshot written by `--save-snapshot` (e.g. the previous day's
+    /// end-of-day state) before processing.
+    #[arg(long, value_name = "PATH")]
+    pub load_snapshot: Option<PathBuf>,
+
+    /// Writes a checksummed snapshot of every account, ledger and disputes included, to this file
+    /// once processing completes.
+    #[arg(long, value_name = "PATH")]
+    pub save_snapshot: Option<PathBuf>,
+
+    /// Periodically records a checkpoint (input position plus every account) to this file, so an
+    /// interrupted run can be continued with `--resume`.
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Number of rows between two checkpoints.
+    #[arg(long, value_name = "ROWS", default_value_t = 100_000, requires = "checkpoint")]
+    pub checkpoint_interval: u64,
+
+    /// Continues from the checkpoint file instead of starting over, when it exists. The inputs
+    /// must be the same as in the interrupted run.
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+
+    /// Writes every rejected row and transaction, with a stable reason code, to this file. With
+    /// `--resume` the report of the interrupted run is continued rather than rewritten.
+    #[arg(long, value_name = "PATH")]
+    pub rejects: Option<PathBuf>,
+
+    /// Format of the rejects report: csv or jsonl. Inferred from the file extension by default.
+    #[arg(long, value_name = "FORMAT", requires = "rejects")]
+    pub rejects_format: Option<RejectsFormat>,
+
+    /// Appends every domain event (deposits, withdrawals, transfers, holds, disputes and locks) to
+    /// this JSON Lines file. Events of previous runs are kept, the file is never rewritten.
+    #[arg(long, value_name = "PATH", conflicts_with = "resume")]
+    pub events: Option<PathBuf>,
+
+    /// Prints the ordered history of this client to STDOUT instead of the account states: every
+    /// applied and rejected transaction with the available, held and total funds after it. With
+    /// `--resume` it only covers the rows processed after the checkpoint.
+    #[arg(long, value_name = "CLIENT")]
+    pub audit: Option<AccountId>,
+
+    /// Verifies the hash chained history of every stored account instead of writing the account
+    /// states, failing on the first broken link. Inputs are optional, any given are processed first.
+    #[arg(long, conflicts_with = "audit")]
+    pub verify: bool,
+
+    /// Allows withdrawals to be disputed, resolved and charged back, and not only deposits.
+    #[arg(long)]
+    pub dispute_withdrawals: bool,
+
+    /// Where the IDs of amount-bearing transactions (deposits, withdrawals, transfers and
+    /// authorizations) must be unique: off, account or global.
+    #[arg(long, value_name = "SCOPE", default_value = "account")]
+    pub idempotency: IdempotencyScope,
+
+    /// Releases authorization holds that were neither captured nor released within this many
+    /// seconds. Holds never expire by default.
+    #[arg(long, value_name = "SECONDS")]
+    pub hold_expiry: Option<u64>,
+
+    /// Processes as of this Unix time in seconds instead of the system clock, so hold expiries
+    /// (and the histories recording them) are reproducible.
+    #[arg(long, value_name = "UNIX_SECONDS")]
+    pub now: Option<u64>,
+
+    /// Maximum number of decimals of an input amount (0 to 28). Unlimited by default.
+    #[arg(long, value_name = "DECIMALS", value_parser = clap::value_parser!(u32).range(0..=28))]
+    pub max_scale: Option<u32>,
+
+    /// What happens to amounts with more decimals than `--max-scale`: reject or round.
+    #[arg(long, value_name = "MODE", default_value = "reject", requires = "max_scale")]
+    pub excess_scale: ExcessScale,
+
+    /// How amounts are rounded, on input and on output: bankers, half-up or truncate.
+    #[arg(long, value_name = "MODE", default_value = "truncate")]
+    pub rounding: RoundingMode,
+
+    /// Number of decimals the balances are written with (0 to 28).
+    #[arg(long, value_name = "DECIMALS", default_value_t = 4, value_parser = clap::value_parser!(u32).range(0..=28))]
+    pub output_scale: u32,
+
+    /// Prints a JSON summary of the run (counts per type and reason, timings) to STDERR.
+    #[arg(long)]
+    pub report: bool
+}
+
+impl Cli {
+    /// Takes a log level passed after the inputs, the way it was passed before `--log-level` existed
+    /// (e.g. `transactions.csv debug`).
+    ///
+    /// Only a trailing `error`, `warn`, `info`, `debug` or `trace` that is not an existing path is taken.
+    pub fn take_legacy_log_level(&mut self) -> Option<LevelFilter> {
+        let [_, .., last] = self.inputs.as_slice() else {
+            return None
+        };
+
+        let level = match last.to_lowercase().as_str() {
+            "error" => LevelFilter::ERROR,
+            "warn" => LevelFilter::WARN,
+            "info" => LevelFilter::INFO,
+            "debug" => LevelFilter::DEBUG,
+            "trace" => LevelFilter::TRACE,
+            _ => return None
+        };
+
+        if Path::new(last).exists() {
+            return None
+        }
+
+        self.inputs.pop();
+
+        Some(level)
+    }
+}
@@ -1,118 +1,448 @@
-use std::fs::File;
-use std::io::BufReader;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::iter::once;
+use std::mem::{replace, take};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
-use csv::{ReaderBuilder, Trim};
+use anyhow::{anyhow, Result};
 use moka::future::Cache;
-use tokio::sync::mpsc;
+use moka::notification::RemovalCause;
+use tokio::pin;
+use tokio::sync::{mpsc, OnceCell};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::task::{spawn_blocking, JoinHandle};
-use tracing::{debug, error};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{debug, error, info};
 
-use crate::actors::AccountActor;
-use crate::models::Transaction;
-use crate::storage::Storage;
+use crate::actors::{AccountActor, ActorContext, Envelope, TransferLeg};
+use crate::audit::AuditTrail;
+use crate::checkpoint::{Checkpoint, InputPosition};
+use crate::engine::reader::read_inputs;
+use crate::engine::{Clock, Input, InputFormat, SystemClock, TransactionIndex};
+use crate::events::{EventLog, EventsChannel};
+use crate::models::{Account, AccountPolicy, Transaction, TransactionType};
+use crate::rejects::{RejectsChannel, RejectsWriter};
+use crate::report::RunReport;
+use crate::storage::{AsyncStorage, StorageError};
 use crate::types::AccountId;
 
 /// High-performance async transaction processing engine.
-pub struct AsyncEngine<S: Storage> {
+pub struct AsyncEngine<S: AsyncStorage> {
     storage: Arc<S>,
     backpressure: usize,
     cache_capacity: u64,
-    cache_timeout: Duration
+    cache_timeout: Duration,
+    rejects: Option<Arc<Mutex<RejectsWriter>>>,
+    input_format: Option<InputFormat>,
+    events: Option<Arc<dyn EventLog>>,
+    audit: Option<Arc<AuditTrail>>,
+    policy: AccountPolicy,
+    clock: Arc<dyn Clock>,
+    index: Arc<TransactionIndex>,
+    seeded: OnceCell<()>,
+    storage_attempts: u32,
+    storage_backoff: Duration,
+    checkpoint_path: Option<PathBuf>,
+    checkpoint_interval: u64,
+    resume: Option<Checkpoint>
 }
 
-#[allow(dead_code)]
-impl<S: Storage> AsyncEngine<S> {
+/// Checkpointing state of a single run over inputs.
+struct Checkpoints {
+    path: PathBuf,
+    interval: u64,
+    inputs: Vec<String>,
+    position: InputPosition,
+    written: u64
+}
+
+impl<S: AsyncStorage> AsyncEngine<S> {
+    /// Creates an engine persisting account state into `storage`.
     pub fn new(storage: Arc<S>) -> Self {
         Self {
             storage,
             backpressure: 256,
             cache_capacity: 5000,
-            cache_timeout: Duration::from_mins(5)
+            cache_timeout: Duration::from_mins(5),
+            rejects: None,
+            input_format: None,
+            events: None,
+            audit: None,
+            policy: AccountPolicy::default(),
+            clock: Arc::new(SystemClock),
+            index: Arc::new(TransactionIndex::new()),
+            seeded: OnceCell::new(),
+            storage_attempts: 5,
+            storage_backoff: Duration::from_millis(100),
+            checkpoint_path: None,
+            checkpoint_interval: 100_000,
+            resume: None
         }
     }
 
+    /// Sets the maximum number of actors kept in memory before the least recently used is passivated.
     pub fn with_cache_capacity(mut self, capacity: u64) -> Self {
         self.cache_capacity = capacity;
         self
     }
 
+    /// Sets how long an actor may stay idle before it is passivated.
     pub fn with_cache_timeout(mut self, timeout: Duration) -> Self {
         self.cache_timeout = timeout;
         self
     }
 
-    /// Orchestrates the end-to-end transaction processing pipeline for a CSV file.
-    pub async fn run(&self, path: &str) -> Result<()> {
-        let (sender, receiver) = mpsc::channel::<Transaction>(self.backpressure);
-        let csv_handle = self.spawn_csv_reader(path.to_string(), sender);
-        let processing_result = self.process_transactions(receiver).await;
+    /// Sets the capacity of the bounded ingestion channel.
+    pub fn with_backpressure(mut self, backpressure: usize) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
 
-        if let Err(error) = csv_handle.await {
-            error!("CSV ingestion failed: {error}");
-        }
+    /// Sets the business rules applied by every account.
+    pub fn with_policy(mut self, policy: AccountPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
 
-        processing_result
+    /// Sets the clock authorization holds expire against, the system clock by default.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
-    fn spawn_csv_reader(&self, path: String, sender: mpsc::Sender<Transaction>) -> JoinHandle<()> {
-        spawn_blocking(move || {
-            let file = match File::open(&path) {
-                Ok(file) => file,
-                Err(error) => {
-                    error!("Error opening CSV at path: {path} | {error}");
-                    return;
-                }
-            };
+    /// Sets how many times a failed storage load or save is attempted, and the delay before the
+    /// first retry (doubled after every failure). A failure past the last attempt aborts the run.
+    pub fn with_storage_retries(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.storage_attempts = attempts.max(1);
+        self.storage_backoff = backoff;
+        self
+    }
 
-            let mut reader = ReaderBuilder::new()
-                .trim(Trim::All)
-                .flexible(true)
-                .from_reader(BufReader::new(file));
-
-            for result in reader.deserialize::<Transaction>() {
-                match result {
-                    Ok(transaction) => {
-                        if sender.blocking_send(transaction).is_err() {
-                            break;
-                        }
-                    }
-                    Err(error) => {
-                        error!("CSV deserialization error: {error}");
-                    }
-                }
-            }
+    /// Records a [`Checkpoint`] into `path` every `interval` rows read by [`AsyncEngine::run_inputs`],
+    /// and once more when the inputs are exhausted.
+    ///
+    /// Every actor is passivated before a checkpoint is taken, so the recorded state holds exactly
+    /// the rows before the checkpointed position. Streamed transactions are never checkpointed.
+    pub fn with_checkpoints(mut self, path: impl Into<PathBuf>, interval: u64) -> Self {
+        self.checkpoint_path = Some(path.into());
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    /// Resumes [`AsyncEngine::run_inputs`] from a checkpoint of an interrupted run over the same inputs.
+    ///
+    /// The checkpointed accounts are restored into the storage, which is expected to be fresh, and
+    /// reading continues right after the last checkpointed row so no transaction is applied twice.
+    pub fn with_resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    /// Reads every input of [`AsyncEngine::run_inputs`] in `format`, instead of the format matching its extension.
+    pub fn with_input_format(mut self, format: InputFormat) -> Self {
+        self.input_format = Some(format);
+        self
+    }
+
+    /// Reports every rejected row and transaction into `writer`.
+    pub fn with_rejects(mut self, writer: RejectsWriter) -> Self {
+        self.rejects = Some(Arc::new(Mutex::new(writer)));
+        self
+    }
+
+    /// Appends the [`AccountEvent`](crate::AccountEvent)s of every applied transaction to `log`.
+    pub fn with_event_log(mut self, log: Arc<dyn EventLog>) -> Self {
+        self.events = Some(log);
+        self
+    }
+
+    /// Records every transaction addressed to the client of `trail`, applied or rejected, together
+    /// with the balances after it.
+    pub fn with_audit_trail(mut self, trail: Arc<AuditTrail>) -> Self {
+        self.audit = Some(trail);
+        self
+    }
+
+    /// Creates a bounded ingestion channel sized by the engine's backpressure setting.
+    ///
+    /// The receiver is meant to be handed to [`AsyncEngine::run_receiver`], while the sender
+    /// can be cloned across any number of producers. Processing completes once every sender is dropped.
+    pub fn channel(&self) -> (mpsc::Sender<Transaction>, mpsc::Receiver<Transaction>) {
+        mpsc::channel(self.backpressure)
+    }
+
+    /// Orchestrates the end-to-end transaction processing pipeline for a CSV or JSON Lines file.
+    pub async fn run(&self, path: &str) -> Result<RunReport> {
+        self.run_inputs(vec![Input::File(PathBuf::from(path))]).await
+    }
+
+    /// Orchestrates the end-to-end transaction processing pipeline for several inputs.
+    ///
+    /// The inputs are read one after another and processed as a single ordered stream.
+    pub async fn run_inputs(&self, inputs: Vec<Input>) -> Result<RunReport> {
+        let timer = Instant::now();
+        let names: Vec<String> = inputs.iter().map(ToString::to_string).collect();
+
+        let start = match &self.resume {
+            Some(checkpoint) => self.restore(checkpoint, &names).await?,
+            None => InputPosition::default()
+        };
+
+        let checkpoints = self.checkpoint_path.clone().map(|path| Checkpoints {
+            path,
+            interval: self.checkpoint_interval,
+            inputs: names,
+            position: start,
+            written: 0
+        });
+
+        let rejects = RejectsChannel::open(self.rejects.as_ref());
+        let events = EventsChannel::open(self.events.as_ref());
+        let (sender, receiver) = mpsc::channel::<Envelope>(self.backpressure);
+        let reader_handle = self.spawn_reader(inputs, start, sender, &rejects);
+        let processing_result = self.process_transactions(ReceiverStream::new(receiver), &rejects, &events, checkpoints).await;
+
+        let parse_failures = reader_handle.await.unwrap_or_else(|error| {
+            error!("Input ingestion failed: {error}");
+            0
+        });
+
+        rejects.close().await;
+        events.close().await;
+
+        processing_result.map(|mut report| {
+            report.rows_read += parse_failures;
+            report.parse_failures = parse_failures;
+            report.timings.total = timer.elapsed();
+            report
+        })
+    }
+
+    /// Processes transactions from any source until the stream is exhausted.
+    pub async fn run_stream(&self, transactions: impl Stream<Item = Transaction>) -> Result<RunReport> {
+        let timer = Instant::now();
+        let rejects = RejectsChannel::open(self.rejects.as_ref());
+        let events = EventsChannel::open(self.events.as_ref());
+        let processing_result = self.process_transactions(transactions.map(Envelope::from), &rejects, &events, None).await;
+
+        rejects.close().await;
+        events.close().await;
+
+        processing_result.map(|mut report| {
+            report.timings.total = timer.elapsed();
+            report
         })
     }
 
-    async fn process_transactions(&self, mut receiver: mpsc::Receiver<Transaction>) -> Result<()> {
-        let (guard_sender, mut guard_receiver) = mpsc::channel::<()>(1);
+    /// Processes transactions sent through a channel created by [`AsyncEngine::channel`]
+    /// until every sender has been dropped.
+    pub async fn run_receiver(&self, receiver: mpsc::Receiver<Transaction>) -> Result<RunReport> {
+        self.run_stream(ReceiverStream::new(receiver)).await
+    }
+
+    fn spawn_reader(&self, inputs: Vec<Input>, start: InputPosition, sender: mpsc::Sender<Envelope>, rejects: &RejectsChannel) -> JoinHandle<u64> {
+        let rejects = rejects.sender();
+        let format = self.input_format;
+        let origins = self.audit.is_some();
+
+        spawn_blocking(move || read_inputs(inputs, format, start, sender, rejects, origins))
+    }
+
+    /// Restores the accounts and transaction ownership of a checkpoint, returning where reading continues.
+    async fn restore(&self, checkpoint: &Checkpoint, inputs: &[String]) -> Result<InputPosition> {
+        if checkpoint.inputs() != inputs {
+            return Err(anyhow!("Checkpoint was recorded for inputs {:?}, not {:?}", checkpoint.inputs(), inputs))
+        }
+
+        for account in checkpoint.accounts() {
+            for transaction_id in account.ledger.keys() {
+                self.index.claim(*transaction_id, account.account_id);
+            }
+
+            self.storage.save(account.account_id, account.clone()).await?;
+        }
+
+        info!("Restored [{}] accounts from checkpoint, resuming at line [{}]", checkpoint.accounts().len(), checkpoint.position().line);
+
+        Ok(checkpoint.position())
+    }
+
+    async fn write_checkpoint(&self, checkpoints: &mut Checkpoints, rejects: &RejectsChannel) -> Result<()> {
+        //NOTE: A resumed run keeps the reported rejections of the rows before the checkpoint, so they must be durable first
+        rejects.sync().await?;
+
+        let accounts: Vec<Account> = self.storage.scan().collect::<Result<_, _>>().await?;
+        let checkpoint = Checkpoint::new(checkpoints.inputs.clone(), checkpoints.position, accounts);
+        let path = checkpoints.path.clone();
+
+        spawn_blocking(move || checkpoint.write(&path)).await??;
+
+        checkpoints.written += 1;
+        debug!("Recorded checkpoint at line [{}] of input [{}]", checkpoints.position.line, checkpoints.position.input);
+
+        Ok(())
+    }
+
+    fn build_cache(&self, passivated: &Arc<AtomicU64>) -> Cache<AccountId, UnboundedSender<Envelope>> {
+        let eviction_counter = passivated.clone();
 
-        let cache: Cache<AccountId, UnboundedSender<Transaction>> = Cache::builder()
+        Cache::builder()
             .time_to_idle(self.cache_timeout)
             .max_capacity(self.cache_capacity)
-            .eviction_listener(|key, _value, cause| {
+            .eviction_listener(move |key, _value, cause| {
                 debug!("Actor for client [{key:?}] was despawned for reason: [{cause:?}]");
+
+                if matches!(cause, RemovalCause::Expired | RemovalCause::Size) {
+                    eviction_counter.fetch_add(1, Ordering::Relaxed);
+                }
             })
-            .build();
+            .build()
+    }
+
+    /// Passivates every actor and waits until all of them have saved their state.
+    ///
+    /// Afterwards the storage holds exactly the transactions dispatched so far, and dispatching
+    /// continues on a fresh cache and guard.
+    async fn quiesce(&self, cache: &mut Cache<AccountId, UnboundedSender<Envelope>>, passivated: &Arc<AtomicU64>, guard_sender: &mut mpsc::Sender<()>, guard_receiver: &mut mpsc::Receiver<()>) {
+        cache.invalidate_all();
+        cache.run_pending_tasks().await;
+        drop(replace(cache, self.build_cache(passivated)));
+
+        let (sender, receiver) = mpsc::channel::<()>(1);
+        drop(replace(guard_sender, sender));
+        replace(guard_receiver, receiver).recv().await;
+    }
+
+    /// Attaches the debit side to a transfer between two clients, returning the credit side for the destination.
+    ///
+    /// Both sides are dispatched right away so each actor reaches its side in input order, see [`TransferLeg`].
+    /// Transfers without a valid destination are left alone and rejected by the source.
+    fn split_transfer(envelope: &mut Envelope) -> Option<(AccountId, Envelope)> {
+        let transaction = &envelope.transaction;
+
+        let destination_id = transaction.destination_id
+            .filter(|destination_id| transaction.transaction_type == TransactionType::Transfer && *destination_id != transaction.account_id)?;
+
+        let (debit, credit) = TransferLeg::pair();
+        envelope.leg = Some(debit);
+
+        Some((destination_id, Envelope {
+            transaction: envelope.transaction.clone(),
+            origin: envelope.origin.clone(),
+            position: None,
+            leg: Some(credit),
+            claim: None
+        }))
+    }
+
+    /// Claims the transaction IDs of every stored account, so ownership carries over from previous runs.
+    ///
+    /// IDs already reused across clients by earlier runs outside of global idempotency are kept by
+    /// whichever client the storage yields first.
+    async fn seed_index(&self) -> Result<()> {
+        let accounts = self.storage.scan();
+        let mut seeded = 0;
+
+        pin!(accounts);
+
+        while let Some(account) = accounts.next().await {
+            let account = account?;
+
+            for transaction_id in account.ledger.keys() {
+                self.index.claim(*transaction_id, account.account_id);
+            }
+
+            seeded += 1;
+        }
+
+        debug!("Seeded the transaction index from [{seeded}] stored accounts");
+
+        Ok(())
+    }
+
+    async fn process_transactions(&self, envelopes: impl Stream<Item = Envelope>, rejects: &RejectsChannel, events: &EventsChannel, mut checkpoints: Option<Checkpoints>) -> Result<RunReport> {
+        //NOTE: Only once per engine, later runs continue with the index they built
+        self.seeded.get_or_try_init(|| self.seed_index()).await?;
+
+        let timer = Instant::now();
+        let (mut guard_sender, mut guard_receiver) = mpsc::channel::<()>(1);
+        let failure = Arc::new(OnceLock::<StorageError>::new());
+        let context = ActorContext::new(self.storage.clone())
+            .with_rejects(rejects.sender())
+            .with_events(events.sender())
+            .with_audit(self.audit.clone())
+            .with_policy(self.policy)
+            .with_clock(self.clock.clone())
+            .with_storage_retries(self.storage_attempts, self.storage_backoff)
+            .with_failure(failure.clone());
+
+        let passivated = Arc::new(AtomicU64::new(0));
+        let spawned = AtomicU64::new(0);
+        let mut received = 0;
+        let mut touched = HashSet::new();
+        let mut cache = self.build_cache(&passivated);
+
+        pin!(envelopes);
 
         //NOTE: In a production system, this loop mimics a Kafka consumer stream. Partitioning by an agreed upon ID ensures strict ordering per client.
-        while let Some(transaction) = receiver.recv().await {
-            let account_id = transaction.account_id;
-            let transaction_id = transaction.transaction_id;
+        while let Some(mut envelope) = envelopes.next().await {
+            //NOTE: A storage failure is fatal, stop consuming so no further state depends on a backend that is down
+            if failure.get().is_some() {
+                break;
+            }
+
+            let account_id = envelope.transaction.account_id;
+            let transaction_id = envelope.transaction.transaction_id;
+            let position = envelope.position;
+
+            received += 1;
+
+            //NOTE: Claims are made here rather than in the actors so the first client in input order wins an ID
+            //      and references see every ID claimed before them
+            envelope.claim = if envelope.transaction.transaction_type.is_amount_bearing() {
+                Some(self.index.claim(transaction_id, account_id))
+            } else {
+                self.index.lookup(transaction_id, account_id)
+            };
+
+            let credit = Self::split_transfer(&mut envelope);
+
+            for (account_id, envelope) in once((account_id, envelope)).chain(credit) {
+                touched.insert(account_id);
+
+                let sender = cache.get_with(account_id, async {
+                    spawned.fetch_add(1, Ordering::Relaxed);
+                    AccountActor::spawn(account_id, context.clone(), guard_sender.clone())
+                }).await;
+
+                if sender.send(envelope).is_err() {
+                    error!("Account actor for client [{}] could not accept transaction [{}]", account_id, transaction_id);
+                }
+            }
+
+            if let Some(checkpoints) = &mut checkpoints && let Some(position) = position {
+                checkpoints.position = position;
+
+                if received % checkpoints.interval == 0 {
+                    self.quiesce(&mut cache, &passivated, &mut guard_sender, &mut guard_receiver).await;
 
-            let sender = cache.get_with(account_id, async {
-                AccountActor::spawn(transaction.account_id, self.storage.clone(), guard_sender.clone())
-            }).await;
+                    //NOTE: Never record a checkpoint over state that failed to persist
+                    if failure.get().is_some() {
+                        break;
+                    }
 
-            if sender.send(transaction).is_err() {
-                error!("Account actor for client [{}] could not accept transaction [{}]", account_id, transaction_id);
+                    if let Err(error) = self.write_checkpoint(checkpoints, rejects).await {
+                        error!("Failed to record checkpoint: {error:#}");
+                    }
+                }
             }
         }
 
+        let ingestion = timer.elapsed();
+
         // Invalidate and process pending tasks to trigger cache evictions
         cache.invalidate_all();
         cache.run_pending_tasks().await;
@@ -120,9 +450,30 @@ impl<S: Storage> AsyncEngine<S> {
         // Explicitly drop the cache to cause all actors to drop their guard sender
         drop(cache);
 
+        let actor_report = context.report.clone();
+
+        drop(context);
         drop(guard_sender);
         guard_receiver.recv().await;
 
-        Ok(())
+        if let Some(error) = Arc::into_inner(failure).and_then(OnceLock::into_inner) {
+            return Err(anyhow!(error).context("Processing aborted after a storage failure"))
+        }
+
+        if let Some(checkpoints) = &mut checkpoints
+            && let Err(error) = self.write_checkpoint(checkpoints, rejects).await {
+            error!("Failed to record checkpoint: {error:#}");
+        }
+
+        let mut report = take(&mut *actor_report.lock().unwrap_or_else(PoisonError::into_inner));
+        report.rows_read = received;
+        report.accounts_touched = touched.len() as u64;
+        report.actors_spawned = spawned.into_inner();
+        report.actors_passivated = passivated.load(Ordering::Relaxed);
+        report.checkpoints_written = checkpoints.map_or(0, |checkpoints| checkpoints.written);
+        report.timings.ingestion = ingestion;
+        report.timings.passivation = timer.elapsed() - ingestion;
+
+        Ok(report)
     }
 }
@@ -0,0 +1,30 @@
+use std::time::SystemTime;
+
+/// The source of the time authorization holds expire against.
+///
+/// The engine reads it once per transaction and hands the time to the account, so the account
+/// itself never reads the wall clock. A [`FixedClock`] makes the expiries recorded in histories,
+/// and therefore their hashes, reproducible across runs.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The wall clock of the machine, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock standing still at a given time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
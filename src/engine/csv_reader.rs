@@ -0,0 +1,144 @@
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use csv::{Position, Reader, ReaderBuilder, StringRecord, Trim};
+use tracing::{debug, error, info};
+
+use crate::actors::Envelope;
+use crate::checkpoint::InputPosition;
+use crate::engine::reader::ReaderOutput;
+use crate::engine::Input;
+use crate::models::Transaction;
+
+/// Reads a single CSV input with a header row, starting at `start` when a run is resumed part way through it.
+///
+/// Failures are added to `parse_failures`, and reading breaks once the engine stopped accepting transactions.
+pub fn read_csv(index: usize, input: &Input, start: Option<InputPosition>, output: &ReaderOutput, parse_failures: &mut u64) -> ControlFlow<()> {
+    let source = match input.open() {
+        Ok(source) => source,
+        Err(error) => {
+            error!("{error:#}");
+            return ControlFlow::Continue(())
+        }
+    };
+
+    debug!("Reading transactions from [{input}]");
+
+    let mut reader = build_reader(source, true);
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => {
+            error!("CSV header error in [{input}]: {error}");
+            return ControlFlow::Continue(())
+        }
+    };
+
+    let mut offset = Offset::default();
+
+    if let Some(start) = start {
+        match resume(input, reader, start) {
+            Ok((resumed, resumed_offset)) => {
+                reader = resumed;
+                offset = resumed_offset;
+            }
+            Err(error) => {
+                error!("Failed to resume [{input}] at line [{}]: {error:#}", start.line);
+                return ControlFlow::Continue(())
+            }
+        }
+    }
+
+    let name: Arc<str> = Arc::from(input.to_string());
+    let client_index = headers.iter().position(|header| header == "client");
+    let tx_index = headers.iter().position(|header| header == "tx");
+    let mut record = StringRecord::new();
+
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(error) => {
+                error!("CSV read error in [{input}]: {error}");
+                *parse_failures += 1;
+                continue;
+            }
+        }
+
+        let position = offset.apply(index, reader.position());
+        let line = record.position().map_or(0, |position| offset.line + position.line());
+        let origin = output.origin(&name, line, || join_record(&record));
+
+        match record.deserialize::<Transaction>(Some(&headers)) {
+            Ok(transaction) => output.send(Envelope { transaction, origin, position: Some(position), leg: None, claim: None })?,
+            Err(error) => {
+                error!("CSV deserialization error in [{input}]: {error}");
+                *parse_failures += 1;
+
+                let account_id = client_index.and_then(|index| record.get(index)).and_then(|field| field.parse().ok());
+                let transaction_id = tx_index.and_then(|index| record.get(index)).and_then(|field| field.parse().ok());
+
+                output.reject_malformed(origin.as_ref(), account_id, transaction_id, error.to_string());
+            }
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Translates positions of a reader that was opened part way through an input into positions in the whole input.
+#[derive(Default)]
+struct Offset {
+    byte: u64,
+    line: u64,
+    record: u64
+}
+
+impl Offset {
+    fn apply(&self, index: usize, position: &Position) -> InputPosition {
+        InputPosition {
+            input: index,
+            byte: self.byte + position.byte(),
+            line: self.line + position.line(),
+            record: self.record + position.record()
+        }
+    }
+}
+
+fn build_reader(source: Box<dyn Read + Send>, has_headers: bool) -> Reader<Box<dyn Read + Send>> {
+    ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true)
+        .has_headers(has_headers)
+        .from_reader(source)
+}
+
+/// Moves the reader to a checkpointed position, seeking files and skipping the rows already read on STDIN.
+fn resume(input: &Input, mut reader: Reader<Box<dyn Read + Send>>, start: InputPosition) -> anyhow::Result<(Reader<Box<dyn Read + Send>>, Offset)> {
+    info!("Resuming [{input}] at line [{}]", start.line);
+
+    if let Input::File(_) = input {
+        let offset = Offset {
+            byte: start.byte,
+            line: start.line - 1,
+            record: start.record
+        };
+
+        return Ok((build_reader(input.open_at(start.byte)?, false), offset))
+    }
+
+    let mut record = StringRecord::new();
+
+    while reader.position().record() < start.record {
+        if let Ok(false) = reader.read_record(&mut record) {
+            break;
+        }
+    }
+
+    Ok((reader, Offset::default()))
+}
+
+fn join_record(record: &StringRecord) -> String {
+    record.iter().collect::<Vec<_>>().join(",")
+}
@@ -0,0 +1,179 @@
+use std::fmt::{Display, Formatter};
+use std::fs::{read_dir, File};
+use std::io::{stdin, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error, Result};
+
+const STDIN_ARGUMENT: &str = "-";
+const SUPPORTED_EXTENSIONS: [&str; 3] = ["csv", "jsonl", "ndjson"];
+
+/// A source of CSV or JSON Lines transactions.
+///
+/// Multiple inputs are processed as one ordered stream, in the order they are given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Input {
+    /// The standard input of the process.
+    Stdin,
+    /// A file on the local filesystem.
+    File(PathBuf)
+}
+
+impl Input {
+    /// Resolves a command line argument into one or more inputs.
+    ///
+    /// - `-` is the standard input.
+    /// - A directory expands to the supported files it contains, sorted by file name.
+    /// - A glob pattern (e.g. `drops/2026-*.jsonl`) expands to the matching files, sorted by path.
+    /// - Anything else is treated as a single file path, which must exist.
+    ///
+    /// # Errors
+    /// Returns an error if a directory cannot be read, a pattern is invalid or matches nothing, or a file does not exist.
+    pub fn resolve(argument: &str) -> Result<Vec<Input>> {
+        if argument == STDIN_ARGUMENT {
+            return Ok(vec![Input::Stdin])
+        }
+
+        let path = Path::new(argument);
+
+        if path.is_dir() {
+            return Self::expand_directory(path)
+        }
+
+        if !path.exists() && argument.contains(['*', '?', '[']) {
+            return Self::expand_pattern(argument)
+        }
+
+        if !path.exists() {
+            return Err(anyhow!("No input found at path: {}", path.display()))
+        }
+
+        Ok(vec![Input::File(path.to_path_buf())])
+    }
+
+    /// Resolves every argument in order, see [`Input::resolve`].
+    pub fn resolve_all<T: AsRef<str>>(arguments: &[T]) -> Result<Vec<Input>> {
+        let mut inputs = Vec::new();
+
+        for argument in arguments {
+            inputs.extend(Self::resolve(argument.as_ref())?);
+        }
+
+        Ok(inputs)
+    }
+
+    pub(crate) fn open(&self) -> Result<Box<dyn Read + Send>> {
+        match self {
+            Input::Stdin => Ok(Box::new(stdin())),
+            Input::File(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("Error opening input at path: {}", path.display()))?;
+
+                Ok(Box::new(BufReader::new(file)))
+            }
+        }
+    }
+
+    /// Opens the input positioned at `byte`, only files can be repositioned.
+    pub(crate) fn open_at(&self, byte: u64) -> Result<Box<dyn Read + Send>> {
+        match self {
+            Input::Stdin => Err(anyhow!("STDIN cannot be repositioned")),
+            Input::File(path) => {
+                let mut file = File::open(path)
+                    .with_context(|| format!("Error opening input at path: {}", path.display()))?;
+
+                file.seek(SeekFrom::Start(byte))?;
+
+                Ok(Box::new(BufReader::new(file)))
+            }
+        }
+    }
+
+    fn expand_directory(path: &Path) -> Result<Vec<Input>> {
+        let mut files = Vec::new();
+
+        for entry in read_dir(path).with_context(|| format!("Error reading input directory: {}", path.display()))? {
+            let file = entry?.path();
+
+            if file.is_file() && Self::is_supported(&file) {
+                files.push(file);
+            }
+        }
+
+        files.sort();
+
+        Ok(files.into_iter().map(Input::File).collect())
+    }
+
+    fn expand_pattern(pattern: &str) -> Result<Vec<Input>> {
+        let mut files = Vec::new();
+
+        for entry in glob::glob(pattern).with_context(|| format!("Invalid input pattern: {pattern}"))? {
+            let file = entry?;
+
+            if file.is_file() {
+                files.push(file);
+            }
+        }
+
+        if files.is_empty() {
+            return Err(anyhow!("No inputs matched pattern: {pattern}"))
+        }
+
+        files.sort();
+
+        Ok(files.into_iter().map(Input::File).collect())
+    }
+
+    fn is_supported(path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SUPPORTED_EXTENSIONS.iter().any(|supported| extension.eq_ignore_ascii_case(supported)))
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Stdin => write!(formatter, "<stdin>"),
+            Input::File(path) => write!(formatter, "{}", path.display())
+        }
+    }
+}
+
+/// The format of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InputFormat {
+    /// Comma separated values with a `type,client,tx,amount` header row.
+    Csv,
+    /// One JSON transaction per line, amounts as decimal strings.
+    JsonLines
+}
+
+impl InputFormat {
+    /// Infers the format from the file extension, `.jsonl` and `.ndjson` are JSON Lines, everything else (STDIN included) is CSV.
+    pub fn from_input(input: &Input) -> Self {
+        match input {
+            Input::File(path) => match path.extension().and_then(|extension| extension.to_str()) {
+                Some(extension) if extension.eq_ignore_ascii_case("jsonl") || extension.eq_ignore_ascii_case("ndjson") => Self::JsonLines,
+                _ => Self::Csv
+            },
+            Input::Stdin => Self::Csv
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            _ => Err(anyhow!("Invalid input format '{format}', expected 'csv' or 'jsonl'"))
+        }
+    }
+}
\ No newline at end of file
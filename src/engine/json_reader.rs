@@ -0,0 +1,156 @@
+use std::io::{BufRead, BufReader, Read};
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tracing::{debug, error, info};
+
+use crate::actors::Envelope;
+use crate::checkpoint::InputPosition;
+use crate::engine::reader::ReaderOutput;
+use crate::engine::Input;
+use crate::models::{Currency, Transaction, TransactionType};
+use crate::types::{AccountId, TransactionId};
+
+/// A single line of a JSON Lines input, e.g. `{"type":"deposit","client":1,"tx":1,"amount":"1.5"}`.
+#[derive(Deserialize)]
+struct JsonTransaction {
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    #[serde(rename = "client")]
+    account_id: AccountId,
+    #[serde(rename = "tx")]
+    transaction_id: TransactionId,
+    #[serde(default, deserialize_with = "deserialize_amount")]
+    amount: Option<Decimal>,
+    #[serde(default)]
+    currency: Option<Currency>,
+    #[serde(rename = "to", default)]
+    destination_id: Option<AccountId>
+}
+
+/// Reads a single JSON Lines input, one transaction per line, starting at `start` when a run is
+/// resumed part way through it. Blank lines are skipped.
+///
+/// Failures are added to `parse_failures`, and reading breaks once the engine stopped accepting transactions.
+pub fn read_json_lines(index: usize, input: &Input, start: Option<InputPosition>, output: &ReaderOutput, parse_failures: &mut u64) -> ControlFlow<()> {
+    let opened = match start {
+        Some(start) => resume(input, start),
+        None => input.open().map(|source| (BufReader::new(source), InputPosition { input: index, line: 1, ..InputPosition::default() }))
+    };
+
+    let (mut reader, mut position) = match opened {
+        Ok(opened) => opened,
+        Err(error) => {
+            error!("{error:#}");
+            return ControlFlow::Continue(())
+        }
+    };
+
+    debug!("Reading transactions from [{input}]");
+
+    let name: Arc<str> = Arc::from(input.to_string());
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+
+        let read = match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) => {
+                error!("JSON Lines read error in [{input}]: {error}");
+                *parse_failures += 1;
+                break;
+            }
+        };
+
+        let line = position.line;
+
+        position.byte += read as u64;
+        position.line += 1;
+        position.record += 1;
+
+        let row = match std::str::from_utf8(&buffer) {
+            Ok(row) => row.trim(),
+            Err(error) => {
+                error!("JSON Lines read error in [{input}] at line [{line}]: {error}");
+                *parse_failures += 1;
+                continue;
+            }
+        };
+
+        if row.is_empty() {
+            continue;
+        }
+
+        let origin = output.origin(&name, line, || row.to_string());
+
+        match serde_json::from_str::<JsonTransaction>(row) {
+            Ok(parsed) => {
+                let mut transaction = Transaction::new(parsed.transaction_type, parsed.account_id, parsed.transaction_id, parsed.amount);
+                transaction.currency = parsed.currency;
+                transaction.destination_id = parsed.destination_id;
+                output.send(Envelope { transaction, origin, position: Some(position), leg: None, claim: None })?;
+            }
+            Err(error) => {
+                error!("JSON Lines deserialization error in [{input}] at line [{line}]: {error}");
+                *parse_failures += 1;
+
+                let value = serde_json::from_str::<Value>(row).ok();
+                let field = |name: &str| value.as_ref().and_then(|value| value.get(name)).and_then(Value::as_u64);
+
+                output.reject_malformed(
+                    origin.as_ref(),
+                    field("client").and_then(|client| client.try_into().ok()),
+                    field("tx").and_then(|tx| tx.try_into().ok()),
+                    error.to_string()
+                );
+            }
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Opens the input at a checkpointed position, seeking files and skipping the lines already read on STDIN.
+fn resume(input: &Input, start: InputPosition) -> anyhow::Result<(BufReader<Box<dyn Read + Send>>, InputPosition)> {
+    info!("Resuming [{input}] at line [{}]", start.line);
+
+    if let Input::File(_) = input {
+        return Ok((BufReader::new(input.open_at(start.byte)?), start))
+    }
+
+    let mut reader = BufReader::new(input.open()?);
+    let mut buffer = Vec::new();
+
+    for _ in 0..start.record {
+        buffer.clear();
+
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+    }
+
+    Ok((reader, start))
+}
+
+/// Accepts amounts as decimal strings (or integers), refusing JSON floats which may already have lost precision.
+fn deserialize_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Decimal>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(amount)) if amount.trim().is_empty() => Ok(None),
+        Some(Value::String(amount)) => Decimal::from_str(amount.trim())
+            .map(Some)
+            .map_err(|error| D::Error::custom(format!("invalid amount '{amount}': {error}"))),
+        Some(Value::Number(amount)) if amount.is_i64() || amount.is_u64() => Decimal::from_str(&amount.to_string())
+            .map(Some)
+            .map_err(|error| D::Error::custom(format!("invalid amount '{amount}': {error}"))),
+        Some(Value::Number(amount)) => Err(D::Error::custom(format!("amount {amount} must be a string to avoid floating point precision loss"))),
+        Some(amount) => Err(D::Error::custom(format!("invalid amount {amount}, expected a decimal string")))
+    }
+}
\ No newline at end of file
@@ -1,5 +1,14 @@
 mod async_engine;
+mod clock;
+mod csv_reader;
+mod input;
+mod json_reader;
+mod reader;
+mod transaction_index;
 #[cfg(test)]
 mod tests;
 
 pub use async_engine::AsyncEngine;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use input::{Input, InputFormat};
+pub(crate) use transaction_index::{Claim, TransactionIndex};
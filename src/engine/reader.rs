@@ -0,0 +1,79 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use crate::actors::{Envelope, Origin};
+use crate::checkpoint::InputPosition;
+use crate::engine::csv_reader::read_csv;
+use crate::engine::json_reader::read_json_lines;
+use crate::engine::{Input, InputFormat};
+use crate::rejects::{Rejection, RejectsSender};
+use crate::types::{AccountId, TransactionId};
+
+/// Streams every input, in order, into the engine's ingestion channel.
+///
+/// Every input is read in `format`, or in the format matching its extension when none is forced.
+/// Reading starts at `start`, which is the beginning of the first input unless a run is resumed
+/// from a checkpoint. Rows that cannot be deserialized are logged and skipped, and reported as
+/// rejections when a rejects report is configured. The [`Origin`] of every row is attached when `origins`
+/// is set or rejections are reported. This is a blocking function and must run on a blocking thread.
+///
+/// Returns the number of rows that failed to parse.
+pub fn read_inputs(inputs: Vec<Input>, format: Option<InputFormat>, start: InputPosition, sender: mpsc::Sender<Envelope>, rejects: Option<RejectsSender>, origins: bool) -> u64 {
+    let output = ReaderOutput {
+        sender,
+        origins: origins || rejects.is_some(),
+        rejects
+    };
+
+    let mut parse_failures = 0;
+
+    for (index, input) in inputs.into_iter().enumerate().skip(start.input) {
+        let resume = (index == start.input && start.record > 0).then_some(start);
+
+        let flow = match format.unwrap_or_else(|| InputFormat::from_input(&input)) {
+            InputFormat::Csv => read_csv(index, &input, resume, &output, &mut parse_failures),
+            InputFormat::JsonLines => read_json_lines(index, &input, resume, &output, &mut parse_failures)
+        };
+
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    parse_failures
+}
+
+/// Where the readers of every format send what they read.
+pub struct ReaderOutput {
+    sender: mpsc::Sender<Envelope>,
+    rejects: Option<RejectsSender>,
+    origins: bool
+}
+
+impl ReaderOutput {
+    /// Sends a transaction to the engine, breaking once the engine stopped accepting transactions.
+    pub fn send(&self, envelope: Envelope) -> ControlFlow<()> {
+        match self.sender.blocking_send(envelope) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(())
+        }
+    }
+
+    //NOTE: The original row is only kept around when somebody is going to report on it
+    pub fn origin(&self, input: &Arc<str>, line: u64, row: impl FnOnce() -> String) -> Option<Origin> {
+        self.origins.then(|| Origin {
+            input: input.clone(),
+            line,
+            row: row()
+        })
+    }
+
+    /// Reports a row that could not be parsed into a transaction.
+    pub fn reject_malformed(&self, origin: Option<&Origin>, account_id: Option<AccountId>, transaction_id: Option<TransactionId>, message: String) {
+        if let (Some(rejects), Some(origin)) = (&self.rejects, origin) {
+            rejects.send(Rejection::malformed(origin, account_id, transaction_id, message));
+        }
+    }
+}
\ No newline at end of file
@@ -1,17 +1,26 @@
-use super::AsyncEngine;
+use super::{AsyncEngine, FixedClock, Input, InputFormat};
 
 use anyhow::{anyhow, Result};
 use std::fs;
 use std::io::Write;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, UNIX_EPOCH};
 
 use rust_decimal::Decimal;
-use tempfile::NamedTempFile;
+use tempfile::{tempdir, NamedTempFile};
+use tokio::spawn;
 use tokio::time::sleep;
+use tokio_stream::Stream;
 
-use crate::storage::{AccountStorage, Storage};
+use crate::audit::AuditTrail;
+use crate::checkpoint::Checkpoint;
+use crate::events::{EventLog, MemoryEventLog};
+use crate::models::{Account, AccountEvent, AccountPolicy, Currency, IdempotencyScope, Transaction, TransactionType};
+use crate::rejects::{RejectsFormat, RejectsWriter};
+use crate::storage::{AccountStorage, ReadableStorage, Storage, StorageError};
+use crate::types::AccountId;
 
 fn create_temporary_csv(transactions: &[(&str, u16, u32, &str)]) -> Result<NamedTempFile> {
     let mut file = NamedTempFile::new()?;
@@ -29,6 +38,48 @@ fn create_temporary_csv(transactions: &[(&str, u16, u32, &str)]) -> Result<Named
     Ok(file)
 }
 
+/// Storage whose saves fail a given number of times before succeeding.
+struct FlakyStorage {
+    inner: AccountStorage,
+    failures: AtomicU32
+}
+
+impl FlakyStorage {
+    fn new(failures: u32) -> Self {
+        Self {
+            inner: AccountStorage::new(),
+            failures: AtomicU32::new(failures)
+        }
+    }
+}
+
+impl crate::storage::AsyncStorage for FlakyStorage {
+    async fn load(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        Ok(Storage::load(&self.inner, account_id))
+    }
+
+    async fn save(&self, account_id: AccountId, account: Account) -> Result<(), StorageError> {
+        if self.failures.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| failures.checked_sub(1)).is_ok() {
+            return Err(StorageError::unavailable("backend is down"))
+        }
+
+        Storage::save(&self.inner, account_id, account);
+        Ok(())
+    }
+
+    async fn get(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        Ok(ReadableStorage::get(&self.inner, account_id))
+    }
+
+    fn scan(&self) -> impl Stream<Item = Result<Account, StorageError>> + Send {
+        tokio_stream::iter(ReadableStorage::scan(&self.inner).map(Ok))
+    }
+
+    async fn count(&self) -> Result<usize, StorageError> {
+        Ok(ReadableStorage::count(&self.inner))
+    }
+}
+
 #[tokio::test]
 async fn test_engine_processes_valid_csv_stream_successfully() -> Result<()> {
     let csv_content = "type,client,tx,amount\ndeposit,1,1,10.0\ndeposit,2,2,20.0\nwithdrawal,1,3,5.0";
@@ -150,3 +201,742 @@ async fn test_cache_time_eviction() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_engine_processes_transactions_from_a_stream() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone());
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::from_str("10.0")?),
+        Transaction::deposit(2, 2, Decimal::from_str("20.0")?),
+        Transaction::withdrawal(1, 3, Decimal::from_str("4.0")?)
+    ]);
+
+    engine.run_stream(transactions).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("6.0")?);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::from_str("20.0")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_processes_transactions_from_multiple_senders() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_backpressure(1);
+
+    let (sender, receiver) = engine.channel();
+
+    let producers: Vec<_> = (1..=4u16).map(|client| {
+        let sender = sender.clone();
+
+        spawn(async move {
+            for transaction_id in 0..10u32 {
+                let transaction = Transaction::deposit(client, u32::from(client) * 100 + transaction_id, Decimal::ONE);
+                sender.send(transaction).await.expect("engine should accept transactions");
+            }
+        })
+    }).collect();
+
+    drop(sender);
+
+    engine.run_receiver(receiver).await?;
+
+    for producer in producers {
+        producer.await?;
+    }
+
+    for client in 1..=4u16 {
+        assert_eq!(storage.load(client).unwrap().available, Decimal::from(10));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_processes_multiple_inputs_as_one_ordered_stream() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone());
+
+    let first = create_temporary_csv(&[
+        ("deposit", 1, 1, "10.0")
+    ])?;
+
+    let second = create_temporary_csv(&[
+        ("withdrawal", 1, 2, "10.0"),
+        ("deposit", 1, 3, "5.0")
+    ])?;
+
+    let inputs = vec![Input::File(first.path().to_path_buf()), Input::File(second.path().to_path_buf())];
+    engine.run_inputs(inputs).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("5.0")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_processes_json_lines_input() -> Result<()> {
+    let directory = tempdir()?;
+    let input = directory.path().join("transactions.jsonl");
+
+    fs::write(&input, concat!(
+        "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"10.1234\"}\n",
+        "\n",
+        "{\"type\":\"withdrawal\",\"client\":1,\"tx\":2,\"amount\":\"0.1234\"}\n",
+        "{\"type\":\"dispute\",\"client\":1,\"tx\":1,\"amount\":null}\n",
+        "{\"type\":\"deposit\",\"client\":2,\"tx\":3,\"amount\":7}\n"
+    ))?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let report = AsyncEngine::new(storage.clone())
+        .run_inputs(vec![Input::File(input)]).await?;
+
+    let account = storage.load(1).unwrap();
+
+    assert_eq!(report.rows_read, 4);
+    assert_eq!(account.available, Decimal::from_str("-0.1234")?);
+    assert_eq!(account.held, Decimal::from_str("10.1234")?);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::from_str("7")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_rejects_json_lines_float_amounts() -> Result<()> {
+    let report = NamedTempFile::new()?;
+    let input = NamedTempFile::new()?;
+
+    fs::write(input.path(), concat!(
+        "{\"type\":\"deposit\",\"client\":1,\"tx\":1,\"amount\":\"1.5\"}\n",
+        "{\"type\":\"deposit\",\"client\":1,\"tx\":2,\"amount\":0.1}\n",
+        "not json\n"
+    ))?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let run = AsyncEngine::new(storage.clone())
+        .with_input_format(InputFormat::JsonLines)
+        .with_rejects(RejectsWriter::create(report.path(), RejectsFormat::JsonLines)?)
+        .run_inputs(vec![Input::File(input.path().to_path_buf())]).await?;
+
+    let contents = fs::read_to_string(report.path())?;
+    let mut rejections: Vec<serde_json::Value> = contents.lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    rejections.sort_by_key(|rejection| rejection["line"].as_u64());
+
+    assert_eq!(run.parse_failures, 2);
+    assert_eq!(rejections.len(), 2);
+    assert_eq!(rejections[0]["reason"], "E_MALFORMED_ROW");
+    assert_eq!(rejections[0]["line"], 2);
+    assert_eq!(rejections[0]["tx"], 2);
+    assert!(rejections[0]["message"].as_str().unwrap_or_default().contains("must be a string"));
+    assert_eq!(rejections[1]["line"], 3);
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("1.5")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reads_csv_and_json_lines_inputs_by_extension() -> Result<()> {
+    let directory = tempdir()?;
+    fs::write(directory.path().join("1.csv"), "type,client,tx,amount\ndeposit,1,1,10.0\n")?;
+    fs::write(directory.path().join("2.ndjson"), "{\"type\":\"withdrawal\",\"client\":1,\"tx\":2,\"amount\":\"4.0\"}\n")?;
+    fs::write(directory.path().join("3.jsonl"), "{\"type\":\"deposit\",\"client\":1,\"tx\":3,\"amount\":\"1.5\"}\n")?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let inputs = Input::resolve(directory.path().to_str().unwrap())?;
+
+    assert_eq!(inputs.len(), 3);
+    assert_eq!(InputFormat::from_input(&inputs[1]), InputFormat::JsonLines);
+    assert_eq!(InputFormat::from_input(&Input::Stdin), InputFormat::Csv);
+
+    AsyncEngine::new(storage.clone()).run_inputs(inputs).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("7.5")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reads_optional_currency_column() -> Result<()> {
+    let directory = tempdir()?;
+    let csv = directory.path().join("1.csv");
+    let jsonl = directory.path().join("2.jsonl");
+
+    fs::write(&csv, "type,client,tx,amount,currency\ndeposit,1,1,10.0,\ndeposit,1,2,5.0,usd\ndeposit,1,3,1.0,US D\ndispute,1,2,,\n")?;
+    fs::write(&jsonl, "{\"type\":\"withdrawal\",\"client\":1,\"tx\":4,\"amount\":\"2.0\",\"currency\":\"EUR\"}\n{\"type\":\"deposit\",\"client\":1,\"tx\":5,\"amount\":\"3.0\",\"currency\":\"EUR\"}\n")?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let report = AsyncEngine::new(storage.clone())
+        .run_inputs(vec![Input::File(csv), Input::File(jsonl)]).await?;
+
+    let account = storage.load(1).unwrap();
+    let usd = Currency::from_str("USD")?;
+    let eur = Currency::from_str("EUR")?;
+
+    assert_eq!(report.parse_failures, 1);
+    assert_eq!(report.rejected_total(), 1);
+    assert_eq!(account.available(), Decimal::from_str("10.0")?);
+    assert_eq!(account.balance(Some(&usd)).held(), Decimal::from_str("5.0")?);
+    assert_eq!(account.balance(Some(&eur)).available(), Decimal::from_str("3.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_input_resolves_directories_in_file_name_order() -> Result<()> {
+    let directory = tempdir()?;
+    fs::write(directory.path().join("2026-01-02.csv"), "")?;
+    fs::write(directory.path().join("2026-01-01.csv"), "")?;
+    fs::write(directory.path().join("notes.txt"), "")?;
+
+    let inputs = Input::resolve(directory.path().to_str().unwrap())?;
+
+    assert_eq!(inputs, vec![
+        Input::File(directory.path().join("2026-01-01.csv")),
+        Input::File(directory.path().join("2026-01-02.csv"))
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn test_input_resolves_stdin_and_glob_patterns() -> Result<()> {
+    let directory = tempdir()?;
+    fs::write(directory.path().join("b.csv"), "")?;
+    fs::write(directory.path().join("a.csv"), "")?;
+
+    let pattern = directory.path().join("*.csv");
+    let inputs = Input::resolve_all(&["-", pattern.to_str().unwrap()])?;
+
+    assert_eq!(inputs, vec![
+        Input::Stdin,
+        Input::File(directory.path().join("a.csv")),
+        Input::File(directory.path().join("b.csv"))
+    ]);
+
+    assert!(Input::resolve(directory.path().join("*.missing").to_str().unwrap()).is_err());
+    assert!(Input::resolve(directory.path().join("missing.csv").to_str().unwrap()).is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reports_rejected_rows_and_transactions() -> Result<()> {
+    let report = NamedTempFile::new()?;
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_rejects(RejectsWriter::create(report.path(), RejectsFormat::JsonLines)?);
+
+    let file = create_temporary_csv(&[
+        ("deposit", 1, 1, "10.0"),
+        ("withdrawal", 1, 2, "50.0"),
+        ("deposit", 1, 3, "abc"),
+        ("deposit", 1, 4, "5.0")
+    ])?;
+
+    engine.run(file.path().to_str().unwrap()).await?;
+
+    let contents = fs::read_to_string(report.path())?;
+    let mut rejections: Vec<serde_json::Value> = contents.lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    rejections.sort_by_key(|rejection| rejection["line"].as_u64());
+
+    assert_eq!(rejections.len(), 2);
+    assert_eq!(rejections[0]["reason"], "E_INSUFFICIENT_FUNDS");
+    assert_eq!(rejections[0]["line"], 3);
+    assert_eq!(rejections[0]["row"], "withdrawal,1,2,50.0");
+    assert_eq!(rejections[1]["reason"], "E_MALFORMED_ROW");
+    assert_eq!(rejections[1]["line"], 4);
+    assert_eq!(rejections[1]["client"], 1);
+    assert_eq!(rejections[1]["tx"], 3);
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("15.0")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_returns_run_report() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_cache_capacity(1);
+
+    let file = create_temporary_csv(&[
+        ("deposit", 1, 1, "10.0"),
+        ("deposit", 2, 2, "10.0"),
+        ("withdrawal", 2, 3, "50.0"),
+        ("dispute", 1, 1, ""),
+        ("chargeback", 1, 1, ""),
+        ("deposit", 1, 4, "abc")
+    ])?;
+
+    let report = engine.run(file.path().to_str().unwrap()).await?;
+
+    assert_eq!(report.rows_read, 6);
+    assert_eq!(report.parse_failures, 1);
+    assert_eq!(report.applied[&TransactionType::Deposit], 2);
+    assert_eq!(report.applied[&TransactionType::Chargeback], 1);
+    assert_eq!(report.rejected[&TransactionType::Withdrawal], 1);
+    assert_eq!(report.rejected_by_reason["E_INSUFFICIENT_FUNDS"], 1);
+    assert_eq!(report.accounts_touched, 2);
+    assert_eq!(report.accounts_locked, 1);
+    assert!(report.actors_spawned >= 2);
+    assert!(report.timings.total >= report.timings.ingestion);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_rejects_transaction_ids_reused_across_accounts_when_global() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_policy(AccountPolicy::default().with_idempotency(IdempotencyScope::Global));
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::deposit(2, 1, Decimal::TEN),
+        Transaction::withdrawal(2, 2, Decimal::from(20)),
+        Transaction::deposit(2, 2, Decimal::ONE)
+    ]);
+
+    let report = engine.run_stream(transactions).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::TEN);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::ONE);
+    assert_eq!(report.rejected_by_reason["E_DUPLICATE_TRANSACTION"], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_gives_reused_transaction_ids_to_the_first_client_in_input_when_global() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_policy(AccountPolicy::default().with_idempotency(IdempotencyScope::Global));
+
+    //NOTE: The backlog keeps the actor of client 1 busy long after client 2 received its deposit
+    let backlog = (100..20_100).map(|transaction_id| Transaction::deposit(1, transaction_id, Decimal::ONE));
+    let transactions = tokio_stream::iter(backlog.chain([
+        Transaction::deposit(1, 7, Decimal::from(5)),
+        Transaction::deposit(2, 7, Decimal::from(9))
+    ]));
+
+    let report = engine.run_stream(transactions).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from(20_005));
+    assert_eq!(storage.load(2).unwrap().available, Decimal::ZERO);
+    assert_eq!(report.rejected_by_reason["E_DUPLICATE_TRANSACTION"], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_keeps_transaction_ids_of_rejected_transactions_reserved_when_global() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_policy(AccountPolicy::default().with_idempotency(IdempotencyScope::Global));
+
+    //NOTE: The first run completes before the reuse is dispatched, so the outcome cannot depend on actor timing
+    let report = engine.run_stream(tokio_stream::iter(vec![Transaction::withdrawal(1, 7, Decimal::from(5))])).await?;
+
+    assert_eq!(report.rejected_by_reason["E_INSUFFICIENT_FUNDS"], 1);
+
+    let report = engine.run_stream(tokio_stream::iter(vec![
+        Transaction::deposit(2, 7, Decimal::from(9)),
+        Transaction::deposit(1, 7, Decimal::from(5))
+    ])).await?;
+
+    assert_eq!(report.rejected_by_reason["E_DUPLICATE_TRANSACTION"], 1);
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from(5));
+    assert_eq!(storage.load(2).unwrap().available, Decimal::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_seeds_transaction_ownership_from_the_storage() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 7, Decimal::from(5)))?;
+    storage.save(1, account);
+
+    let engine = AsyncEngine::new(storage.clone())
+        .with_policy(AccountPolicy::default().with_idempotency(IdempotencyScope::Global));
+
+    let report = engine.run_stream(tokio_stream::iter(vec![
+        Transaction::deposit(2, 7, Decimal::from(9)),
+        Transaction::dispute(2, 7)
+    ])).await?;
+
+    assert_eq!(report.rejected_by_reason["E_DUPLICATE_TRANSACTION"], 1);
+    assert_eq!(report.rejected_by_reason["E_CLIENT_MISMATCH"], 1);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_allows_transaction_ids_reused_across_accounts_by_default() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone());
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::deposit(2, 1, Decimal::TEN)
+    ]);
+
+    engine.run_stream(transactions).await?;
+
+    assert_eq!(storage.load(1).unwrap().available, Decimal::TEN);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::TEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reports_disputes_addressed_to_the_wrong_client() -> Result<()> {
+    let report_file = NamedTempFile::new()?;
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_rejects(RejectsWriter::create(report_file.path(), RejectsFormat::JsonLines)?);
+
+    let file = create_temporary_csv(&[
+        ("deposit", 1, 1, "10.0"),
+        ("deposit", 2, 2, "5.0"),
+        ("withdrawal", 2, 3, "1.0"),
+        ("dispute", 2, 1, ""),
+        ("dispute", 2, 99, "")
+    ])?;
+
+    let report = engine.run(file.path().to_str().unwrap()).await?;
+
+    let contents = fs::read_to_string(report_file.path())?;
+    let mut rejections: Vec<serde_json::Value> = contents.lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    rejections.sort_by_key(|rejection| rejection["line"].as_u64());
+
+    assert_eq!(report.rejected_by_reason["E_CLIENT_MISMATCH"], 1);
+    assert_eq!(report.rejected_by_reason["E_TRANSACTION_NOT_FOUND"], 1);
+    assert_eq!(rejections[0]["reason"], "E_CLIENT_MISMATCH");
+    assert!(rejections[0]["message"].as_str().unwrap().contains("owned by client [1]"));
+    assert_eq!(storage.load(1).unwrap().held, Decimal::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reports_client_mismatch_before_the_owner_applied_its_transaction() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone());
+
+    //NOTE: The backlog keeps the actor of client 1 busy long after client 2 received its dispute
+    let backlog = (100..20_100).map(|transaction_id| Transaction::deposit(1, transaction_id, Decimal::ONE));
+    let transactions = tokio_stream::iter(backlog.chain([
+        Transaction::deposit(1, 7, Decimal::from(5)),
+        Transaction::dispute(2, 7)
+    ]));
+
+    let report = engine.run_stream(transactions).await?;
+
+    assert_eq!(report.rejected_by_reason["E_CLIENT_MISMATCH"], 1);
+    assert!(!report.rejected_by_reason.contains_key("E_TRANSACTION_NOT_FOUND"));
+    assert_eq!(storage.load(1).unwrap().held, Decimal::ZERO);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_retries_failed_saves_with_backoff() -> Result<()> {
+    let storage = Arc::new(FlakyStorage::new(2));
+    let engine = AsyncEngine::new(storage.clone())
+        .with_storage_retries(3, Duration::from_millis(1));
+
+    let file = create_temporary_csv(&[("deposit", 1, 1, "10.0")])?;
+    engine.run(file.path().to_str().unwrap()).await?;
+
+    assert_eq!(storage.inner.load(1).unwrap().available, Decimal::TEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_fails_when_saves_keep_failing() -> Result<()> {
+    let storage = Arc::new(FlakyStorage::new(u32::MAX));
+    let engine = AsyncEngine::new(storage.clone())
+        .with_storage_retries(2, Duration::from_millis(1));
+
+    let file = create_temporary_csv(&[("deposit", 1, 1, "10.0"), ("deposit", 2, 2, "5.0")])?;
+    let error = engine.run(file.path().to_str().unwrap()).await.unwrap_err();
+
+    assert!(error.to_string().contains("storage failure"));
+    assert!(matches!(error.downcast_ref::<StorageError>(), Some(StorageError::Unavailable { .. })));
+    assert!(storage.inner.load(1).is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_resumes_from_checkpoint_without_double_applying() -> Result<()> {
+    let directory = tempdir()?;
+    let input = directory.path().join("transactions.csv");
+    let checkpoint_path = directory.path().join("checkpoint.jsonl");
+
+    let rows = ["deposit,1,1,10.0", "deposit,2,2,5.0", "withdrawal,1,3,2.0", "dispute,2,2,", "deposit,1,4,1.0", "resolve,2,2,", "withdrawal,2,5,1.0"];
+    fs::write(&input, format!("type,client,tx,amount\n{}\n", rows[..4].join("\n")))?;
+
+    // The first run only sees part of the input, as if it was interrupted after its last checkpoint
+    let report = AsyncEngine::new(Arc::new(AccountStorage::new()))
+        .with_checkpoints(&checkpoint_path, 2)
+        .run_inputs(vec![Input::File(input.clone())]).await?;
+
+    assert_eq!(report.checkpoints_written, 3);
+
+    let mut file = fs::OpenOptions::new().append(true).open(&input)?;
+    writeln!(file, "{}", rows[4..].join("\n"))?;
+
+    let checkpoint = Checkpoint::load(&checkpoint_path)?;
+    let storage = Arc::new(AccountStorage::new());
+    let report = AsyncEngine::new(storage.clone())
+        .with_resume(checkpoint)
+        .run_inputs(vec![Input::File(input.clone())]).await?;
+
+    assert_eq!(report.rows_read, 3);
+    assert_eq!(report.rejected_total(), 0);
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("9.0")?);
+    assert_eq!(storage.load(2).unwrap().available, Decimal::from_str("4.0")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_resumes_json_lines_from_checkpoint() -> Result<()> {
+    let directory = tempdir()?;
+    let input = directory.path().join("transactions.jsonl");
+    let checkpoint_path = directory.path().join("checkpoint.jsonl");
+
+    let row = |kind: &str, tx: u32, amount: &str| format!("{{\"type\":\"{kind}\",\"client\":1,\"tx\":{tx},\"amount\":\"{amount}\"}}\n");
+    fs::write(&input, row("deposit", 1, "10.0") + &row("withdrawal", 2, "3.0"))?;
+
+    AsyncEngine::new(Arc::new(AccountStorage::new()))
+        .with_checkpoints(&checkpoint_path, 1)
+        .run_inputs(vec![Input::File(input.clone())]).await?;
+
+    let mut file = fs::OpenOptions::new().append(true).open(&input)?;
+    write!(file, "{}", row("deposit", 3, "0.5"))?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let report = AsyncEngine::new(storage.clone())
+        .with_resume(Checkpoint::load(&checkpoint_path)?)
+        .run_inputs(vec![Input::File(input)]).await?;
+
+    assert_eq!(report.rows_read, 1);
+    assert_eq!(storage.load(1).unwrap().available, Decimal::from_str("7.5")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_refuses_to_resume_over_different_inputs() -> Result<()> {
+    let directory = tempdir()?;
+    let checkpoint_path = directory.path().join("checkpoint.jsonl");
+    let first = create_temporary_csv(&[("deposit", 1, 1, "10.0")])?;
+    let second = create_temporary_csv(&[("deposit", 1, 1, "10.0")])?;
+
+    AsyncEngine::new(Arc::new(AccountStorage::new()))
+        .with_checkpoints(&checkpoint_path, 10)
+        .run(first.path().to_str().unwrap()).await?;
+
+    let result = AsyncEngine::new(Arc::new(AccountStorage::new()))
+        .with_resume(Checkpoint::load(&checkpoint_path)?)
+        .run(second.path().to_str().unwrap()).await;
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_event_log_rebuilds_every_account() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let log = Arc::new(MemoryEventLog::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_cache_capacity(1)
+        .with_event_log(log.clone());
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::from_str("10.0")?),
+        Transaction::deposit(2, 2, Decimal::from_str("20.0")?),
+        Transaction::withdrawal(1, 3, Decimal::from_str("4.0")?),
+        Transaction::dispute(2, 2),
+        Transaction::withdrawal(1, 4, Decimal::from_str("100.0")?),
+        Transaction::chargeback(2, 2),
+        Transaction::deposit(2, 5, Decimal::ONE)
+    ]);
+
+    engine.run_stream(transactions).await?;
+
+    assert_eq!(log.events(1)?.len(), 2);
+    assert_eq!(log.events(2)?.len(), 4);
+
+    for account in ReadableStorage::scan(storage.as_ref()) {
+        assert_eq!(log.rebuild(account.account_id)?, account);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_records_audit_trail_of_one_client() -> Result<()> {
+    let file = create_temporary_csv(&[
+        ("deposit", 1, 1, "10.0"),
+        ("deposit", 2, 2, "5.0"),
+        ("withdrawal", 1, 3, "50.0"),
+        ("dispute", 1, 1, ""),
+        ("chargeback", 1, 1, ""),
+        ("deposit", 1, 4, "1.0")
+    ])?;
+
+    let trail = Arc::new(AuditTrail::new(1));
+    let engine = AsyncEngine::new(Arc::new(AccountStorage::new()))
+        .with_cache_capacity(1)
+        .with_audit_trail(trail.clone());
+
+    engine.run_inputs(vec![Input::File(file.path().to_path_buf())]).await?;
+
+    let entries = trail.entries();
+    let lines: Vec<Option<u64>> = entries.iter().map(|entry| entry.line).collect();
+    let reasons: Vec<Option<&str>> = entries.iter().map(|entry| entry.reason).collect();
+
+    assert_eq!(lines, [Some(2), Some(4), Some(5), Some(6), Some(7)]);
+    assert_eq!(reasons, [None, Some("E_INSUFFICIENT_FUNDS"), None, None, Some("E_ACCOUNT_LOCKED")]);
+    assert!(entries[3].locked);
+    assert_eq!(entries[3].total, Decimal::ZERO);
+
+    Ok(())
+}
+#[tokio::test]
+async fn test_engine_transfers_funds_between_clients_atomically() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let log = Arc::new(MemoryEventLog::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_event_log(log.clone());
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::transfer(1, 2, 2, Decimal::from(4)),
+        Transaction::transfer(2, 1, 3, Decimal::ONE),
+        Transaction::withdrawal(2, 4, Decimal::from(3)),
+        Transaction::transfer(1, 3, 5, Decimal::from(100)),
+        Transaction::deposit(4, 6, Decimal::ONE),
+        Transaction::dispute(4, 6),
+        Transaction::chargeback(4, 6),
+        Transaction::transfer(1, 4, 7, Decimal::from(2)),
+        Transaction::transfer(1, 1, 8, Decimal::ONE)
+    ]);
+
+    let report = engine.run_stream(transactions).await?;
+    let total = |account_id: AccountId| storage.load(account_id).map_or(Decimal::ZERO, |account| account.total());
+
+    assert_eq!(total(1), Decimal::from(7));
+    assert_eq!(total(2), Decimal::ZERO);
+    assert_eq!(total(3), Decimal::ZERO);
+    assert_eq!(total(4), Decimal::ZERO);
+    assert_eq!(report.applied[&TransactionType::Transfer], 2);
+    assert_eq!(report.rejected[&TransactionType::Transfer], 3);
+    assert_eq!(report.rejected_by_reason["E_INSUFFICIENT_FUNDS"], 1);
+    assert_eq!(report.rejected_by_reason["E_DESTINATION_LOCKED"], 1);
+    assert_eq!(report.rejected_by_reason["E_INVALID_DESTINATION"], 1);
+    assert_eq!(report.accounts_touched, 4);
+
+    for account in ReadableStorage::scan(storage.as_ref()) {
+        assert_eq!(log.rebuild(account.account_id)?, account);
+        assert!(account.verify().is_ok());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_reads_transfer_destination_column() -> Result<()> {
+    let mut file = NamedTempFile::new()?;
+
+    writeln!(file, "type,client,tx,amount,to")?;
+    writeln!(file, "deposit,1,1,5.0,")?;
+    writeln!(file, "transfer,1,2,2.5,2")?;
+    writeln!(file, "transfer,2,3,1.0,")?;
+
+    let storage = Arc::new(AccountStorage::new());
+    let report = AsyncEngine::new(storage.clone())
+        .run(file.path().to_str().unwrap())
+        .await?;
+
+    assert_eq!(storage.load(1).unwrap().available(), Decimal::from_str("2.5")?);
+    assert_eq!(storage.load(2).unwrap().available(), Decimal::from_str("2.5")?);
+    assert_eq!(report.rejected_by_reason["E_INVALID_DESTINATION"], 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_expires_authorization_holds() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let log = Arc::new(MemoryEventLog::new());
+    let engine = AsyncEngine::new(storage.clone())
+        .with_policy(AccountPolicy::default().with_hold_expiry(Duration::ZERO))
+        .with_clock(Arc::new(FixedClock(UNIX_EPOCH + Duration::from_secs(1_000))))
+        .with_event_log(log.clone());
+
+    let transactions = tokio_stream::iter(vec![
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::authorize(1, 2, Decimal::from(4)),
+        Transaction::withdrawal(1, 3, Decimal::from(8)),
+        Transaction::authorize(1, 4, Decimal::ONE),
+        Transaction::deposit(2, 5, Decimal::TEN),
+        Transaction::authorize(2, 6, Decimal::from(6))
+    ]);
+
+    let report = engine.run_stream(transactions).await?;
+    let account = storage.load(1).unwrap();
+
+    // Holds without time left expire before the next transaction of the client, or when its actor stops
+    assert_eq!(account.available(), Decimal::from(2));
+    assert_eq!(account.held(), Decimal::ZERO);
+    assert_eq!(storage.load(2).unwrap().available(), Decimal::TEN);
+    assert_eq!(report.holds_expired, 3);
+    assert_eq!(report.applied[&TransactionType::Authorize], 3);
+    assert_eq!(log.rebuild(1)?, account);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_engine_records_the_same_history_under_a_fixed_clock() -> Result<()> {
+    let mut histories = Vec::new();
+
+    for _ in 0..2 {
+        let storage = Arc::new(AccountStorage::new());
+        let engine = AsyncEngine::new(storage.clone())
+            .with_policy(AccountPolicy::default().with_hold_expiry(Duration::from_secs(60)))
+            .with_clock(Arc::new(FixedClock(UNIX_EPOCH + Duration::from_secs(1_000))));
+
+        engine.run_stream(tokio_stream::iter(vec![
+            Transaction::deposit(1, 1, Decimal::TEN),
+            Transaction::authorize(1, 2, Decimal::from(4))
+        ])).await?;
+
+        histories.push(storage.load(1).unwrap().history().to_vec());
+    }
+
+    assert_eq!(histories[0], histories[1]);
+    assert!(matches!(histories[0][1].event(), AccountEvent::Authorized { expires_at: Some(1_060), .. }));
+
+    Ok(())
+}
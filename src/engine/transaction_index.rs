@@ -0,0 +1,58 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+use crate::types::{AccountId, TransactionId};
+
+/// Outcome of claiming a transaction ID for a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// The ID was unknown and now belongs to the client.
+    Claimed,
+    /// The ID already belongs to the same client.
+    Owned,
+    /// The ID already belongs to another client.
+    Foreign(AccountId)
+}
+
+/// Engine-wide map of amount-bearing transaction IDs to the client that owns them.
+///
+/// It backs both global idempotency and the detection of disputes addressed to the wrong client.
+/// The engine claims IDs while dispatching, so ownership follows input order rather than actor scheduling.
+/// Claims are never given back, not even when the transaction is rejected later on, since the outcome
+/// is only known once an actor applied it and another client may have reused the ID in the meantime.
+/// When IDs are reused across clients (outside of global idempotency) the first owner is kept.
+///
+/// The map is sharded, so actors only contend when they touch IDs in the same shard
+/// instead of serializing through a single lock.
+#[derive(Default)]
+pub struct TransactionIndex {
+    owners: DashMap<TransactionId, AccountId>
+}
+
+impl TransactionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Atomically records `account_id` as the owner of `transaction_id` unless it is already owned.
+    pub fn claim(&self, transaction_id: TransactionId, account_id: AccountId) -> Claim {
+        match self.owners.entry(transaction_id) {
+            Entry::Occupied(entry) if *entry.get() == account_id => Claim::Owned,
+            Entry::Occupied(entry) => Claim::Foreign(*entry.get()),
+            Entry::Vacant(entry) => {
+                entry.insert(account_id);
+                Claim::Claimed
+            }
+        }
+    }
+
+    /// The client owning `transaction_id`, if any.
+    pub fn owner(&self, transaction_id: TransactionId) -> Option<AccountId> {
+        self.owners.get(&transaction_id).map(|owner| *owner)
+    }
+
+    /// How `transaction_id` relates to `account_id` without claiming it, `None` when nobody owns it.
+    pub fn lookup(&self, transaction_id: TransactionId, account_id: AccountId) -> Option<Claim> {
+        self.owner(transaction_id).map(|owner_id| if owner_id == account_id { Claim::Owned } else { Claim::Foreign(owner_id) })
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::{spawn_blocking, JoinHandle};
+use tracing::error;
+
+use crate::events::EventLog;
+use crate::models::AccountEvent;
+
+/// Per-run channel funnelling the events emitted by the actors into the event log.
+///
+/// Every actor sends its events in the order they were applied, so the log keeps the order of every client.
+pub struct EventsChannel {
+    sender: Option<UnboundedSender<Vec<AccountEvent>>>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl EventsChannel {
+    /// Spawns the appending task if an event log is configured.
+    pub fn open(log: Option<&Arc<dyn EventLog>>) -> Self {
+        let Some(log) = log.cloned() else {
+            return Self { sender: None, handle: None }
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<AccountEvent>>();
+
+        let handle = spawn_blocking(move || {
+            while let Some(events) = receiver.blocking_recv() {
+                if let Err(error) = log.append(&events) {
+                    error!("Event log append failed: {error}");
+                }
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            handle: Some(handle)
+        }
+    }
+
+    pub fn sender(&self) -> Option<UnboundedSender<Vec<AccountEvent>>> {
+        self.sender.clone()
+    }
+
+    /// Waits for every outstanding event to be appended.
+    ///
+    /// All senders handed out by [`EventsChannel::sender`] must have been dropped beforehand.
+    pub async fn close(mut self) {
+        drop(self.sender.take());
+
+        if let Some(handle) = self.handle.take()
+            && let Err(error) = handle.await {
+            error!("Event log writer failed: {error}");
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::warn;
+
+use crate::events::EventLog;
+use crate::models::AccountEvent;
+use crate::storage::terminate_partial_line;
+use crate::types::AccountId;
+
+/// Durable event log appending one JSON event per line to a file.
+///
+/// Opening an existing file continues appending after its last event, so the log can span several
+/// runs. The events of a transaction are written with a single write, and a partially written last
+/// line (e.g. after a crash) is ignored and dropped on open, while an unreadable line anywhere else
+/// means the log is corrupted and fails to read.
+pub struct FileEventLog {
+    path: PathBuf,
+    file: Mutex<File>
+}
+
+impl FileEventLog {
+    /// Opens (or creates) the log at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened for appending, or holds an unreadable line before the last.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Error opening event log at path: {}", path.display()))?;
+
+        let (_, readable) = read(&path, |_| false)?;
+
+        //NOTE: Drop a partially written last line, the next event would otherwise follow it and make it look corrupted
+        if file.metadata()?.len() > readable {
+            file.set_len(readable)?;
+        }
+
+        terminate_partial_line(&mut file)?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file)
+        })
+    }
+}
+
+impl EventLog for FileEventLog {
+    fn append(&self, events: &[AccountEvent]) -> Result<()> {
+        let mut lines = Vec::new();
+
+        for event in events {
+            serde_json::to_writer(&mut lines, event)?;
+            lines.push(b'\n');
+        }
+
+        self.file.lock().unwrap_or_else(PoisonError::into_inner).write_all(&lines)?;
+
+        Ok(())
+    }
+
+    fn events(&self, account_id: AccountId) -> Result<Vec<AccountEvent>> {
+        let (events, _) = read(&self.path, |event| event.account_id() == account_id)?;
+        Ok(events)
+    }
+}
+
+/// Reads the events of the log at `path` accepted by `keep`, along with the length of the file up
+/// to the end of the last readable line.
+///
+/// Only the last line can be partially written, by a crash or by an append still in progress, so
+/// an unreadable last line is skipped. Any other unreadable line means the log is corrupted.
+fn read(path: &Path, mut keep: impl FnMut(&AccountEvent) -> bool) -> Result<(Vec<AccountEvent>, u64)> {
+    let file = File::open(path)
+        .with_context(|| format!("Error opening event log at path: {}", path.display()))?;
+
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    let mut events = Vec::new();
+    let (mut number, mut offset, mut readable) = (0, 0, 0);
+    let mut unreadable = None;
+
+    loop {
+        line.clear();
+
+        let read = reader.read_line(&mut line)?;
+
+        if read == 0 {
+            break;
+        }
+
+        number += 1;
+        offset += read as u64;
+
+        let record = line.trim_end_matches(['\n', '\r']);
+
+        if record.is_empty() {
+            continue;
+        }
+
+        if let Some((number, error)) = unreadable.take() {
+            return Err(anyhow!("Corrupted event at [{}] line [{number}], only the last line may be partially written: {error}", path.display()))
+        }
+
+        match serde_json::from_str::<AccountEvent>(record) {
+            Ok(event) => {
+                if keep(&event) {
+                    events.push(event);
+                }
+
+                readable = offset;
+            }
+            Err(error) => unreadable = Some((number, error))
+        }
+    }
+
+    if let Some((number, error)) = unreadable {
+        warn!("Skipping partially written last event at [{}] line [{number}]: {error}", path.display());
+    }
+
+    Ok((events, readable))
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use dashmap::DashMap;
+
+use crate::events::EventLog;
+use crate::models::AccountEvent;
+use crate::types::AccountId;
+
+/// In-memory event log, keeping the events of every client for the lifetime of the process.
+#[derive(Default)]
+pub struct MemoryEventLog {
+    events: DashMap<AccountId, Vec<AccountEvent>>
+}
+
+impl MemoryEventLog {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventLog for MemoryEventLog {
+    fn append(&self, events: &[AccountEvent]) -> Result<()> {
+        for event in events {
+            self.events.entry(event.account_id()).or_default().push(event.clone());
+        }
+
+        Ok(())
+    }
+
+    fn events(&self, account_id: AccountId) -> Result<Vec<AccountEvent>> {
+        Ok(self.events.get(&account_id).map(|events| events.clone()).unwrap_or_default())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+mod events_channel;
+mod file_event_log;
+mod memory_event_log;
+#[cfg(test)]
+mod tests;
+
+use anyhow::Result;
+
+use crate::models::{Account, AccountEvent};
+use crate::types::AccountId;
+
+pub(crate) use events_channel::EventsChannel;
+pub use file_event_log::FileEventLog;
+pub use memory_event_log::MemoryEventLog;
+
+/// Append-only log of the [`AccountEvent`]s emitted by the engine.
+///
+/// The events of a single client are appended in the order they were applied, so folding them
+/// rebuilds the account. Events are never updated or removed.
+pub trait EventLog: Send + Sync + 'static {
+    /// Appends the events emitted by a single transaction.
+    fn append(&self, events: &[AccountEvent]) -> Result<()>;
+    /// Reads every event of the client, in the order they were appended.
+    fn events(&self, account_id: AccountId) -> Result<Vec<AccountEvent>>;
+
+    /// Rebuilds the client purely from its events, see [`Account::from_events`].
+    fn rebuild(&self, account_id: AccountId) -> Result<Account> {
+        Ok(Account::from_events(account_id, &self.events(account_id)?))
+    }
+}
\ No newline at end of file
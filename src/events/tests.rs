@@ -0,0 +1,84 @@
+use super::{EventLog, FileEventLog, MemoryEventLog};
+
+use std::fs::{read_to_string, write, OpenOptions};
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use tempfile::tempdir;
+
+use crate::models::{Account, AccountEvent, Transaction};
+
+fn apply_history(log: &impl EventLog) -> Result<Account> {
+    let mut account = Account::new(1);
+
+    for transaction in [
+        Transaction::deposit(1, 1, Decimal::TEN),
+        Transaction::withdrawal(1, 2, Decimal::ONE),
+        Transaction::dispute(1, 1),
+        Transaction::chargeback(1, 1)
+    ] {
+        log.append(&account.apply(&transaction)?)?;
+    }
+
+    log.append(&Account::new(2).apply(&Transaction::deposit(2, 3, Decimal::ONE))?)?;
+
+    Ok(account)
+}
+
+#[test]
+fn test_memory_event_log_rebuilds_accounts() -> Result<()> {
+    let log = MemoryEventLog::new();
+    let account = apply_history(&log)?;
+
+    assert_eq!(log.events(1)?.len(), 5);
+    assert_eq!(log.rebuild(1)?, account);
+    assert_eq!(log.rebuild(2)?.available(), Decimal::ONE);
+    assert!(log.events(3)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_file_event_log_appends_across_reopens() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("events.jsonl");
+
+    let account = apply_history(&FileEventLog::open(&path)?)?;
+
+    assert!(read_to_string(&path)?.starts_with(r#"{"event":"deposited","client":1,"tx":1,"amount":"10"}"#));
+
+    // A crash in the middle of a write leaves a partial line behind
+    OpenOptions::new().append(true).open(&path)?.write_all(br#"{"event":"depos"#)?;
+
+    let log = FileEventLog::open(&path)?;
+
+    assert!(read_to_string(&path)?.lines().all(|line| serde_json::from_str::<AccountEvent>(line).is_ok()), "the partial line must be dropped on open");
+
+    log.append(&[AccountEvent::Deposited { account_id: 2, transaction_id: 4, amount: Decimal::TEN, currency: None }])?;
+
+    assert_eq!(log.rebuild(1)?, account);
+    assert_eq!(log.events(2)?.len(), 2);
+    assert_eq!(log.rebuild(2)?.available(), Decimal::from(11));
+
+    Ok(())
+}
+#[test]
+fn test_file_event_log_refuses_an_unreadable_line_before_the_last() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("events.jsonl");
+
+    let log = FileEventLog::open(&path)?;
+    apply_history(&log)?;
+
+    let mut lines: Vec<String> = read_to_string(&path)?.lines().map(String::from).collect();
+    lines[1].truncate(10);
+    write(&path, lines.join("\n") + "\n")?;
+
+    let error = log.events(1).err().ok_or_else(|| anyhow!("Read a corrupted log"))?;
+
+    assert!(error.to_string().contains("line [2]"));
+    assert!(FileEventLog::open(&path).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! A high-performance, asynchronous transaction processing engine.
+//!
+//! The engine streams transactions into per-client actors, applies them to an
+//! [`Account`] state machine and persists the resulting state through a [`Storage`]
+//! implementation. The same pipeline backs the `async-transaction-engine` binary
+//! and can be embedded in-process:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use async_transaction_engine::{AccountStorage, AsyncEngine};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let storage = Arc::new(AccountStorage::new());
+//! let engine = AsyncEngine::new(storage.clone());
+//!
+//! engine.run("transactions.csv").await?;
+//!
+//! for account in storage.iter() {
+//!     println!("{}: {}", account.account_id(), account.total());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod actors;
+mod audit;
+mod checkpoint;
+mod engine;
+mod events;
+mod models;
+mod output;
+mod rejects;
+mod report;
+mod snapshot;
+mod storage;
+mod types;
+
+pub use audit::{AuditEntry, AuditTrail, Verification};
+pub use checkpoint::{Checkpoint, InputPosition};
+pub use engine::{AsyncEngine, Clock, FixedClock, Input, InputFormat, SystemClock};
+pub use events::{EventLog, FileEventLog, MemoryEventLog};
+pub use models::{
+    Account, AccountError, AccountEvent, AccountPolicy, Balance, ChainError, Currency, ErrorCategory, ExcessScale, HistoryEntry, IdempotencyScope, MigratedState, MoneyPolicy, RoundingMode, Transaction, TransactionType,
+    WithdrawalDisputes
+};
+pub use output::{AccountsWriter, OutputFormat, SortKey};
+pub use rejects::{Rejection, RejectsFormat, RejectsWriter, MALFORMED_ROW};
+pub use report::{PhaseTimings, RunReport};
+pub use snapshot::Snapshot;
+pub use storage::{AccountStorage, AsyncStorage, FileStorage, ReadableStorage, Storage, StorageError};
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteStorage;
+pub use types::{AccountId, TransactionId};
@@ -1,67 +1,160 @@
-mod actors;
-mod engine;
-mod models;
-mod storage;
-mod types;
+mod cli;
 
+use std::fs::File;
 use std::io::{stderr, stdout, BufWriter, Write};
-use std::process::exit;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, UNIX_EPOCH};
 
-use anyhow::Result;
-use tracing::info;
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use tracing::{info, warn};
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{fmt, Layer};
 
-use crate::engine::AsyncEngine;
-use crate::storage::AccountStorage;
+use async_transaction_engine::{
+    AccountPolicy, AccountStorage, AccountsWriter, AsyncEngine, AsyncStorage, AuditTrail, Checkpoint, FileEventLog, FixedClock, FileStorage, Input, MoneyPolicy, OutputFormat, RejectsFormat,
+    RejectsWriter, Snapshot, Verification, WithdrawalDisputes
+};
+
+#[cfg(feature = "sqlite")]
+use async_transaction_engine::SqliteStorage;
+
+use crate::cli::Cli;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    //NOTE: If I was making a much more sophisticated CLI application, I would have used the clap crate
-    //      to handle the CLI parsing and execution.
-    let args: Vec<String> = std::env::args().collect();
+    let mut cli = Cli::parse();
+    let legacy_log_level = cli.take_legacy_log_level();
 
-    if args.len() < 2 {
-        eprintln!("Usage: async-transaction-engine [input].csv [log_level:optional] > [output].csv");
-        eprintln!("Available log levels: error, warn, info, debug, trace (default: error)");
-        exit(1);
+    setup_logging(legacy_log_level.unwrap_or(cli.log_level));
+
+    if let Some(level) = legacy_log_level {
+        warn!("Passing the log level after the inputs is deprecated, use `--log-level {level}` instead");
     }
 
-    let path = &args[1];
-    let log_level = args.get(2)
-        .map(|s| parse_log_level(s)).unwrap_or_else(|| LevelFilter::ERROR);
+    let inputs = Input::resolve_all(&cli.inputs)?;
 
-    setup_logging(log_level);
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &cli.sqlite {
+        let storage = Arc::new(SqliteStorage::open(path)?);
+        let trail = process(&cli, inputs, storage.clone()).await?;
+        return write_output(&cli, storage.as_ref(), trail.as_deref()).await
+    }
 
-    let storage = Arc::new(AccountStorage::new());
-    let engine = AsyncEngine::new(storage.clone());
-    
-    let timer = Instant::now();
-    engine.run(path).await?;
-    let duration = timer.elapsed();
+    match &cli.state {
+        Some(directory) => {
+            let storage = Arc::new(FileStorage::open(directory)?);
+            let trail = process(&cli, inputs, storage.clone()).await?;
+            storage.compact()?;
+            write_output(&cli, storage.as_ref(), trail.as_deref()).await
+        }
+        None => {
+            let storage = Arc::new(AccountStorage::new());
+            let trail = process(&cli, inputs, storage.clone()).await?;
+            write_output(&cli, storage.as_ref(), trail.as_deref()).await
+        }
+    }
+}
+
+/// Runs the engine over the inputs, returning the audit trail when one was requested.
+async fn process<S: AsyncStorage>(cli: &Cli, inputs: Vec<Input>, storage: Arc<S>) -> Result<Option<Arc<AuditTrail>>> {
+    if let Some(path) = &cli.load_snapshot {
+        let snapshot = Snapshot::load(path)?;
+        snapshot.restore(storage.as_ref()).await?;
+
+        info!("Loaded [{}] accounts from snapshot [{}]", snapshot.accounts().len(), path.display());
+    }
+
+    let mut engine = AsyncEngine::new(storage.clone())
+        .with_policy(build_policy(cli));
+
+    if let Some(format) = cli.input_format {
+        engine = engine.with_input_format(format);
+    }
+
+    if let Some(seconds) = cli.now {
+        engine = engine.with_clock(Arc::new(FixedClock(UNIX_EPOCH + Duration::from_secs(seconds))));
+    }
+
+    let mut resumed = None;
+
+    if let Some(path) = &cli.checkpoint {
+        engine = engine.with_checkpoints(path, cli.checkpoint_interval);
+
+        if cli.resume && path.exists() {
+            resumed = Some(Checkpoint::load(path)?);
+        } else if cli.resume {
+            info!("No checkpoint found at [{}], starting from the beginning", path.display());
+        }
+    }
+
+    if let Some(path) = &cli.rejects {
+        let format = cli.rejects_format.unwrap_or_else(|| RejectsFormat::from_path(path));
+        //NOTE: The interrupted run already reported the rows before the checkpoint, keep them
+        let writer = match &resumed {
+            Some(checkpoint) => RejectsWriter::resume(path, format, checkpoint)?,
+            None => RejectsWriter::create(path, format)?
+        };
+        engine = engine.with_rejects(writer);
+    }
 
-    info!("Processed transactions in: {duration:?}");
+    if let Some(checkpoint) = resumed {
+        engine = engine.with_resume(checkpoint);
+    }
+
+    if let Some(path) = &cli.events {
+        engine = engine.with_event_log(Arc::new(FileEventLog::open(path)?));
+    }
+
+    let trail = cli.audit.map(|account_id| Arc::new(AuditTrail::new(account_id)));
+
+    if let Some(trail) = &trail {
+        engine = engine.with_audit_trail(trail.clone());
+    }
     
-    write_results_to_stdout(storage)?;
+    let report = engine.run_inputs(inputs).await?;
 
-    Ok(())
+    info!("Processed transactions in: {:?}", report.timings.total);
+
+    if cli.report {
+        eprintln!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    if let Some(path) = &cli.save_snapshot {
+        Snapshot::capture(storage.as_ref()).await?.write(path)?;
+    }
+
+    Ok(trail)
 }
 
-fn parse_log_level(level: &str) -> LevelFilter {
-    match level.to_lowercase().as_str() {
-        "trace" => LevelFilter::TRACE,
-        "debug" => LevelFilter::DEBUG,
-        "info" => LevelFilter::INFO,
-        "warn" => LevelFilter::WARN,
-        "error" => LevelFilter::ERROR,
-        _ => {
-            eprintln!("Invalid log level '{}', defaulting to 'error'", level);
-            LevelFilter::ERROR
-        }
+fn build_policy(cli: &Cli) -> AccountPolicy {
+    let withdrawal_disputes = if cli.dispute_withdrawals {
+        WithdrawalDisputes::Enabled
+    } else {
+        WithdrawalDisputes::Disabled
+    };
+
+    let policy = AccountPolicy::default()
+        .with_withdrawal_disputes(withdrawal_disputes)
+        .with_idempotency(cli.idempotency)
+        .with_money(build_money(cli));
+
+    match cli.hold_expiry {
+        Some(seconds) => policy.with_hold_expiry(Duration::from_secs(seconds)),
+        None => policy
+    }
+}
+
+fn build_money(cli: &Cli) -> MoneyPolicy {
+    let money = MoneyPolicy::default()
+        .with_rounding(cli.rounding)
+        .with_output_scale(cli.output_scale);
+
+    match cli.max_scale {
+        Some(max_scale) => money.with_max_scale(max_scale, cli.excess_scale),
+        None => money
     }
 }
 
@@ -77,24 +170,84 @@ fn setup_logging(level: LevelFilter) {
         .init();
 }
 
-fn write_results_to_stdout(storage: Arc<AccountStorage>) -> Result<()> {
-    let mut output = BufWriter::new(stdout().lock());
+async fn write_output<S: AsyncStorage>(cli: &Cli, storage: &S, trail: Option<&AuditTrail>) -> Result<()> {
+    if cli.verify {
+        return verify_storage(storage).await
+    }
+
+    let output = open_output(cli)?;
 
-    writeln!(output, "client,available,held,total,locked")?;
-    
-    for account in storage.iter() {
-        writeln!(
-            output,
-            "{},{:.4},{:.4},{:.4},{}",
-            account.account_id,
-            account.available,
-            account.held,
-            account.total(),
-            account.locked
-        )?;
+    match trail {
+        Some(trail) => write_audit_trail(trail, &build_money(cli), output),
+        None => write_results(cli, storage, output).await
+    }
+}
+
+fn open_output(cli: &Cli) -> Result<Box<dyn Write>> {
+    match &cli.output {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Error creating output at path: {}", path.display()))?;
+
+            Ok(Box::new(file))
+        }
+        None => Ok(Box::new(stdout().lock()))
+    }
+}
+
+async fn verify_storage<S: AsyncStorage>(storage: &S) -> Result<()> {
+    let verification = Verification::run(storage).await?;
+
+    if let Some(broken) = verification.first_broken {
+        return Err(anyhow!("{broken} ([{}] of [{}] accounts broken)", verification.broken, verification.broken + verification.verified))
+    }
+
+    println!("Verified [{}] accounts, every history is intact", verification.verified);
+
+    Ok(())
+}
+
+fn write_audit_trail(trail: &AuditTrail, money: &MoneyPolicy, output: Box<dyn Write>) -> Result<()> {
+    let mut output = csv::Writer::from_writer(BufWriter::new(output));
+    let entries = trail.entries();
+
+    info!("Writing [{}] audit entries for client [{}]", entries.len(), trail.account_id());
+
+    output.write_record(["input", "line", "client", "tx", "type", "amount", "currency", "outcome", "reason", "available", "held", "total", "locked"])?;
+
+    for entry in entries {
+        let outcome = if entry.is_applied() { "applied" } else { "rejected" };
+
+        output.serialize((
+            entry.input,
+            entry.line,
+            entry.account_id,
+            entry.transaction_id,
+            entry.transaction_type,
+            entry.amount.map(|amount| money.round_output(amount).to_string()),
+            entry.currency.map(String::from),
+            outcome,
+            entry.reason,
+            money.round_output(entry.available).to_string(),
+            money.round_output(entry.held).to_string(),
+            money.round_output(entry.total).to_string(),
+            entry.locked
+        ))?;
     }
 
     output.flush()?;
 
     Ok(())
 }
+
+async fn write_results<S: AsyncStorage>(cli: &Cli, storage: &S, output: Box<dyn Write>) -> Result<()> {
+    let format = cli.output_format.unwrap_or_else(|| cli.output.as_deref().map_or(OutputFormat::Csv, OutputFormat::from_path));
+    let written = AccountsWriter::new(format)
+        .with_sort(cli.sort)
+        .with_money(build_money(cli))
+        .write(storage, output).await?;
+
+    info!("Wrote [{written}] account balances");
+
+    Ok(())
+}
\ No newline at end of file
@@ -1,30 +1,48 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
-use crate::models::errors::AccountError;
-use crate::models::{DisputeStatus, Transaction, TransactionType};
+use crate::models::errors::{AccountError, ChainError};
+use crate::models::history::link_hash;
+use crate::models::{
+    AccountEvent, AccountPolicy, Balance, Currency, DisputeStatus, EntryKind, HistoryEntry, Hold, HoldStatus, IdempotencyScope, LedgerEntry, MigratedState, Transaction,
+    TransactionType, WithdrawalDisputes
+};
 use crate::types::{AccountId, TransactionId};
 
 /// Represents the state of a single client account.
 ///
-/// This struct manages the balance (available and held), lock status, and
-/// history of the ledger and disputes required for correct transaction processing.
-#[derive(Debug, Clone)]
+/// This struct manages the balances (available and held, per currency), lock status, and
+/// history of the ledger, disputes and authorization holds required for correct transaction
+/// processing. A chargeback in any currency locks the whole account.
+///
+/// The whole state, including the ledger and disputes, is serializable so it can be persisted.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Account {
     /// The unique identifier for the client.
-    pub account_id: AccountId,
-    /// Funds available for withdrawal or trading.
-    pub available: Decimal,
-    /// Funds held due to active disputes.
-    pub held: Decimal,
+    pub(crate) account_id: AccountId,
+    /// Funds of the default balance available for withdrawal or trading.
+    pub(crate) available: Decimal,
+    /// Funds of the default balance held due to active disputes.
+    pub(crate) held: Decimal,
+    /// Balances of transactions carrying a currency, the default balance is not included.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) currencies: BTreeMap<Currency, Balance>,
     /// Whether the account is frozen (due to a chargeback).
-    pub locked: bool,
-    /// History of all successful deposits, mapped by transaction ID.
+    pub(crate) locked: bool,
+    /// History of all successful deposits, withdrawals, transfers and authorizations, mapped by transaction ID.
     /// Used to reference the amount during disputes.
-    ledger: HashMap<TransactionId, Decimal>,
+    pub(crate) ledger: HashMap<TransactionId, LedgerEntry>,
     /// Status of active or past disputes, mapped by transaction ID.
-    disputes: HashMap<TransactionId, DisputeStatus>
+    pub(crate) disputes: HashMap<TransactionId, DisputeStatus>,
+    /// Status of open or closed authorization holds, mapped by the ID of the authorization.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) holds: HashMap<TransactionId, Hold>,
+    /// Every event folded into the account, hash chained so tampering can be detected.
+    #[serde(default)]
+    pub(crate) history: Vec<HistoryEntry>
 }
 
 impl Account {
@@ -34,16 +52,23 @@ impl Account {
             account_id,
             available: Decimal::ZERO,
             held: Decimal::ZERO,
+            currencies: BTreeMap::new(),
             locked: false,
             ledger: HashMap::new(),
-            disputes: HashMap::new()
+            disputes: HashMap::new(),
+            holds: HashMap::new(),
+            history: Vec::new()
         }
     }
 
-    /// Applies a single transaction to the account state.
+    /// Applies a single transaction to the account state using the default [`AccountPolicy`].
     ///
     /// This is the main entry point for business logic. It dispatches to specific
     /// handlers based on the transaction type and enforces the global "locked" check.
+    /// On success the [`AccountEvent`]s describing the change are returned, in the order they were applied.
+    ///
+    /// A transfer only moves the side of this account: it is debited when it is the client of the
+    /// transfer and credited when it is the destination. The engine applies both sides or neither.
     ///
     /// # Errors
     /// Returns `AccountError` if:
@@ -51,118 +76,555 @@ impl Account {
     /// - Insufficient funds for withdrawal.
     /// - Duplicate transaction ID.
     /// - Referenced transaction for dispute/resolve/chargeback is missing.
-    pub fn apply(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
+    pub fn apply(&mut self, transaction: &Transaction) -> Result<Vec<AccountEvent>, AccountError> {
+        self.apply_with(transaction, &AccountPolicy::default())
+    }
+
+    /// Applies a single transaction to the account state under the given policy.
+    ///
+    /// No time is involved, so authorizations are not given an expiry and holds are never
+    /// considered expired. Use [`Account::apply_at`] to apply a hold expiry policy.
+    ///
+    /// # Errors
+    /// See [`Account::apply`].
+    pub fn apply_with(&mut self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        let events = self.decide(transaction, policy, None)?;
+        self.commit(&events);
+
+        Ok(events)
+    }
+
+    /// Applies a single transaction under the given policy as of `now`, the time authorization holds expire against.
+    ///
+    /// Expired holds are not released by this method, see [`Account::expire_holds`].
+    ///
+    /// # Errors
+    /// See [`Account::apply`].
+    pub fn apply_at(&mut self, transaction: &Transaction, policy: &AccountPolicy, now: SystemTime) -> Result<Vec<AccountEvent>, AccountError> {
+        let events = self.decide(transaction, policy, Some(now))?;
+        self.commit(&events);
+
+        Ok(events)
+    }
+
+    /// Releases every open authorization hold whose expiry is at or before `now`, in authorization ID order.
+    ///
+    /// The returned events are already applied. Holds expire on locked accounts too, since they only return funds to the client.
+    pub fn expire_holds(&mut self, now: SystemTime) -> Vec<AccountEvent> {
+        let now = unix_seconds(now);
+
+        let mut expired: Vec<TransactionId> = self.holds.iter()
+            .filter(|(_, hold)| hold.status == HoldStatus::Open && hold.expires_at.is_some_and(|expires_at| expires_at <= now))
+            .map(|(transaction_id, _)| *transaction_id)
+            .collect();
+
+        //NOTE: Holds live in a hash map, sort them so the history does not depend on its iteration order
+        expired.sort_unstable();
+
+        let events: Vec<AccountEvent> = expired.into_iter()
+            .filter_map(|transaction_id| self.holds.get(&transaction_id).map(|hold| (transaction_id, hold)))
+            .filter(|(_, hold)| self.balance(hold.currency.as_ref()).available.checked_add(hold.amount).is_some())
+            .map(|(transaction_id, hold)| AccountEvent::HoldExpired {
+                account_id: self.account_id,
+                transaction_id,
+                amount: hold.amount,
+                currency: hold.currency.clone()
+            })
+            .collect();
+
+        self.commit(&events);
+
+        events
+    }
+
+    /// Runs every check of [`Account::apply_at`] without changing the state, returning the events applying the transaction would emit.
+    ///
+    /// Without a time, holds neither expire nor are given an expiry.
+    pub(crate) fn decide(&self, transaction: &Transaction, policy: &AccountPolicy, now: Option<SystemTime>) -> Result<Vec<AccountEvent>, AccountError> {
+        let now = now.map(unix_seconds);
+
         if self.locked {
+            if self.is_credited_by(transaction) {
+                return Err(AccountError::destination_locked(transaction, self.account_id))
+            }
+
             return Err(AccountError::account_locked(transaction))
         }
 
         match transaction.transaction_type {
-            TransactionType::Deposit => self.deposit(transaction),
-            TransactionType::Withdrawal => self.withdrawal(transaction),
-            TransactionType::Dispute => self.dispute(transaction),
-            TransactionType::Resolve => self.resolve(transaction),
-            TransactionType::Chargeback => self.chargeback(transaction)
+            TransactionType::Deposit => self.deposit(transaction, policy),
+            TransactionType::Withdrawal => self.withdrawal(transaction, policy),
+            TransactionType::Dispute => self.dispute(transaction, policy),
+            TransactionType::Resolve => self.resolve(transaction, policy),
+            TransactionType::Chargeback => self.chargeback(transaction, policy),
+            TransactionType::Transfer if self.is_credited_by(transaction) => self.transfer_in(transaction, policy),
+            TransactionType::Transfer => self.transfer_out(transaction, policy),
+            TransactionType::Authorize => self.authorize(transaction, policy, now),
+            TransactionType::Capture => self.capture(transaction, now),
+            TransactionType::Release => self.release(transaction, now)
         }
     }
 
-    /// Calculates the total funds (available + held).
-    pub fn total(&self) -> Decimal {
-        let mut total = self.available;
-        total += self.held;
-        total
+    /// Folds events returned by [`Account::decide`], as long as nothing was applied in between.
+    pub(crate) fn commit(&mut self, events: &[AccountEvent]) {
+        for event in events {
+            self.record(event);
+        }
     }
 
-    fn deposit(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
-        if self.ledger.contains_key(&transaction.transaction_id) {
-            return Err(AccountError::duplicate_transaction(transaction))
+    /// Rebuilds an account purely by folding its events, in the order they were emitted.
+    ///
+    /// Events of other clients are skipped, so a whole event log can be passed as is.
+    pub fn from_events<'a>(account_id: AccountId, events: impl IntoIterator<Item = &'a AccountEvent>) -> Self {
+        let mut account = Self::new(account_id);
+
+        for event in events.into_iter().filter(|event| event.account_id() == account_id) {
+            account.record(event);
         }
 
-        let Some(amount) = transaction.amount else {
-            return Err(AccountError::amount_required(transaction))
-        };
+        account
+    }
 
-        if amount.is_sign_negative() {
-            return Err(AccountError::negative_amount(transaction))
+    /// Verifies the hash chain of the history, and that folding it reproduces the current state.
+    ///
+    /// # Errors
+    /// Returns the first link whose hash does not match, or a mismatch if the history is intact but
+    /// the balances, ledger or disputes were altered.
+    pub fn verify(&self) -> Result<(), ChainError> {
+        let mut rebuilt = Self::new(self.account_id);
+
+        for (sequence, entry) in self.history.iter().enumerate() {
+            let previous = rebuilt.history.last().map_or("", |entry| entry.hash.as_str());
+
+            if entry.event.account_id() != self.account_id || entry.hash != link_hash(previous, &entry.event) {
+                return Err(ChainError::BrokenLink { account_id: self.account_id, sequence, transaction_id: entry.event.transaction_id() })
+            }
+
+            rebuilt.record(&entry.event);
         }
 
-        self.available = self.available.checked_add(amount)
-            .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.ledger.insert(transaction.transaction_id, amount);
+        if rebuilt != *self {
+            return Err(ChainError::StateMismatch { account_id: self.account_id })
+        }
 
         Ok(())
     }
 
-    fn withdrawal(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
-        let Some(amount) = transaction.amount else {
-            return Err(AccountError::amount_required(transaction))
+    /// Starts the history of an account persisted before histories existed with a
+    /// [`AccountEvent::Migrated`] event holding its current state, so it verifies from there on.
+    ///
+    /// Accounts that already have a history, or no state at all, are left alone.
+    pub(crate) fn migrate_history(&mut self) {
+        if !self.history.is_empty() || *self == Self::new(self.account_id) {
+            return
+        }
+
+        let state = MigratedState {
+            available: self.available,
+            held: self.held,
+            currencies: self.currencies.clone(),
+            locked: self.locked,
+            ledger: self.ledger.iter().map(|(transaction_id, entry)| (*transaction_id, entry.clone())).collect(),
+            disputes: self.disputes.iter().map(|(transaction_id, status)| (*transaction_id, status.clone())).collect(),
+            holds: self.holds.iter().map(|(transaction_id, hold)| (*transaction_id, hold.clone())).collect()
         };
 
-        if amount.is_sign_negative() {
-            return Err(AccountError::negative_amount(transaction))
+        self.record(&AccountEvent::Migrated { account_id: self.account_id, state: Box::new(state) });
+    }
+
+    /// Every event folded into the account, oldest first, see [`HistoryEntry`].
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+
+    /// The unique identifier for the client.
+    pub fn account_id(&self) -> AccountId {
+        self.account_id
+    }
+
+    /// The balance in `currency`, or the default balance when absent.
+    pub fn balance(&self, currency: Option<&Currency>) -> Balance {
+        match currency {
+            None => Balance { available: self.available, held: self.held },
+            Some(currency) => self.currencies.get(currency).copied().unwrap_or_default()
         }
+    }
+
+    /// Every balance of the account, the default balance first and then by currency.
+    ///
+    /// The default balance is left out of accounts that only ever moved funds in a currency.
+    pub fn balances(&self) -> impl Iterator<Item = (Option<&Currency>, Balance)> {
+        let default = self.currencies.is_empty() || self.ledger.values().any(|entry| entry.currency.is_none());
+
+        default.then(|| (None, self.balance(None)))
+            .into_iter()
+            .chain(self.currencies.iter().map(|(currency, balance)| (Some(currency), *balance)))
+    }
+
+    /// Funds of the default balance available for withdrawal or trading.
+    pub fn available(&self) -> Decimal {
+        self.available
+    }
+
+    /// Funds of the default balance held due to active disputes.
+    pub fn held(&self) -> Decimal {
+        self.held
+    }
+
+    /// Whether the account is frozen (due to a chargeback).
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Calculates the total funds of the default balance (available + held).
+    pub fn total(&self) -> Decimal {
+        let mut total = self.available;
+        total += self.held;
+        total
+    }
+
+    fn deposit(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        self.check_not_duplicate(transaction, policy)?;
+        let amount = Self::get_amount(transaction, policy)?;
+        let currency = transaction.currency.clone();
+
+        self.balance(currency.as_ref()).available.checked_add(amount)
+            .ok_or_else(|| AccountError::overflow(transaction))?;
 
-        if self.available < amount {
+        Ok(vec![AccountEvent::Deposited { account_id: self.account_id, transaction_id: transaction.transaction_id, amount, currency }])
+    }
+
+    fn withdrawal(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        self.check_not_duplicate(transaction, policy)?;
+        let amount = Self::get_amount(transaction, policy)?;
+        let currency = transaction.currency.clone();
+        let balance = self.balance(currency.as_ref());
+
+        if balance.available < amount {
             return Err(AccountError::insufficient_funds(transaction))
         }
 
-        self.available = self.available.checked_sub(amount)
+        balance.available.checked_sub(amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
 
-        Ok(())
+        Ok(vec![AccountEvent::Withdrew { account_id: self.account_id, transaction_id: transaction.transaction_id, amount, currency }])
     }
 
-    fn dispute(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
+    fn dispute(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
         if self.disputes.contains_key(&transaction.transaction_id) {
             return Err(AccountError::duplicate_dispute(transaction))
         }
 
-        let amount = self.get_deposit(transaction)?;
+        let entry = self.get_disputable(transaction, policy)?;
+        let balance = self.balance(entry.currency.as_ref());
 
-        self.available = self.available.checked_sub(amount)
+        if entry.kind == EntryKind::Deposit {
+            balance.available.checked_sub(entry.amount)
+                .ok_or_else(|| AccountError::overflow(transaction))?;
+        }
+
+        balance.held.checked_add(entry.amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.held = self.held.checked_add(amount)
+
+        Ok(vec![AccountEvent::FundsHeld {
+            account_id: self.account_id,
+            transaction_id: transaction.transaction_id,
+            disputed: entry.kind.into(),
+            amount: entry.amount,
+            currency: entry.currency
+        }])
+    }
+
+    fn resolve(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        self.check_dispute_in_progress(transaction)?;
+        let entry = self.get_disputable(transaction, policy)?;
+        let balance = self.balance(entry.currency.as_ref());
+
+        if entry.kind == EntryKind::Deposit {
+            balance.available.checked_add(entry.amount)
+                .ok_or_else(|| AccountError::overflow(transaction))?;
+        }
+
+        balance.held.checked_sub(entry.amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.disputes.insert(transaction.transaction_id, DisputeStatus::InProgress);
 
-        Ok(())
+        Ok(vec![AccountEvent::FundsReleased {
+            account_id: self.account_id,
+            transaction_id: transaction.transaction_id,
+            disputed: entry.kind.into(),
+            amount: entry.amount,
+            currency: entry.currency
+        }])
     }
 
-    fn resolve(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
+    fn chargeback(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
         self.check_dispute_in_progress(transaction)?;
-        let amount = self.get_deposit(transaction)?;
+        let entry = self.get_disputable(transaction, policy)?;
+        let balance = self.balance(entry.currency.as_ref());
+
+        if entry.kind == EntryKind::Withdrawal {
+            balance.available.checked_add(entry.amount)
+                .ok_or_else(|| AccountError::overflow(transaction))?;
+        }
 
-        self.available = self.available.checked_add(amount)
+        balance.held.checked_sub(entry.amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.held = self.held.checked_sub(amount)
+
+        Ok(vec![
+            AccountEvent::ChargedBack {
+                account_id: self.account_id,
+                transaction_id: transaction.transaction_id,
+                disputed: entry.kind.into(),
+                amount: entry.amount,
+                currency: entry.currency
+            },
+            AccountEvent::Locked { account_id: self.account_id, transaction_id: transaction.transaction_id }
+        ])
+    }
+
+    fn transfer_out(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        let destination_id = transaction.destination_id
+            .filter(|destination_id| *destination_id != self.account_id)
+            .ok_or_else(|| AccountError::invalid_destination(transaction))?;
+
+        self.check_not_duplicate(transaction, policy)?;
+        let amount = Self::get_amount(transaction, policy)?;
+        let currency = transaction.currency.clone();
+        let balance = self.balance(currency.as_ref());
+
+        if balance.available < amount {
+            return Err(AccountError::insufficient_funds(transaction))
+        }
+
+        balance.available.checked_sub(amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.disputes.insert(transaction.transaction_id, DisputeStatus::Resolved);
 
-        Ok(())
+        Ok(vec![AccountEvent::TransferredOut { account_id: self.account_id, transaction_id: transaction.transaction_id, destination_id, amount, currency }])
     }
 
-    fn chargeback(&mut self, transaction: &Transaction) -> Result<(), AccountError> {
-        self.check_dispute_in_progress(transaction)?;
-        let amount = self.get_deposit(transaction)?;
+    fn transfer_in(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<Vec<AccountEvent>, AccountError> {
+        self.check_not_duplicate(transaction, policy)?;
+        let amount = Self::get_amount(transaction, policy)?;
+        let currency = transaction.currency.clone();
 
-        self.held = self.held.checked_sub(amount)
+        self.balance(currency.as_ref()).available.checked_add(amount)
             .ok_or_else(|| AccountError::overflow(transaction))?;
-            
-        self.locked = true;
-        self.disputes.insert(transaction.transaction_id, DisputeStatus::Chargeback);
 
-        Ok(())
+        Ok(vec![AccountEvent::TransferredIn { account_id: self.account_id, transaction_id: transaction.transaction_id, source_id: transaction.account_id, amount, currency }])
     }
 
-    fn get_deposit(&self, transaction: &Transaction) -> Result<Decimal, AccountError> {
-        self.ledger.get(&transaction.transaction_id).copied()
+    fn authorize(&self, transaction: &Transaction, policy: &AccountPolicy, now: Option<u64>) -> Result<Vec<AccountEvent>, AccountError> {
+        self.check_not_duplicate(transaction, policy)?;
+        let amount = Self::get_amount(transaction, policy)?;
+        let currency = transaction.currency.clone();
+        let balance = self.balance(currency.as_ref());
+
+        if balance.available < amount {
+            return Err(AccountError::insufficient_funds(transaction))
+        }
+
+        balance.held.checked_add(amount)
+            .ok_or_else(|| AccountError::overflow(transaction))?;
+
+        let expires_at = now.zip(policy.hold_expiry).map(|(now, expiry)| now.saturating_add(expiry.as_secs()));
+
+        Ok(vec![AccountEvent::Authorized { account_id: self.account_id, transaction_id: transaction.transaction_id, amount, currency, expires_at }])
+    }
+
+    fn capture(&self, transaction: &Transaction, now: Option<u64>) -> Result<Vec<AccountEvent>, AccountError> {
+        let hold = self.get_open_hold(transaction, now)?;
+
+        self.balance(hold.currency.as_ref()).held.checked_sub(hold.amount)
+            .ok_or_else(|| AccountError::overflow(transaction))?;
+
+        Ok(vec![AccountEvent::Captured {
+            account_id: self.account_id,
+            transaction_id: transaction.transaction_id,
+            amount: hold.amount,
+            currency: hold.currency.clone()
+        }])
+    }
+
+    fn release(&self, transaction: &Transaction, now: Option<u64>) -> Result<Vec<AccountEvent>, AccountError> {
+        let hold = self.get_open_hold(transaction, now)?;
+        let balance = self.balance(hold.currency.as_ref());
+
+        balance.available.checked_add(hold.amount)
+            .ok_or_else(|| AccountError::overflow(transaction))?;
+
+        balance.held.checked_sub(hold.amount)
+            .ok_or_else(|| AccountError::overflow(transaction))?;
+
+        Ok(vec![AccountEvent::HoldReleased {
+            account_id: self.account_id,
+            transaction_id: transaction.transaction_id,
+            amount: hold.amount,
+            currency: hold.currency.clone()
+        }])
+    }
+
+    /// Folds a single event into the state and links it into the history, this is the only place where the state changes.
+    ///
+    /// Events are only emitted once every check (including overflows) has passed, so they always apply.
+    fn record(&mut self, event: &AccountEvent) {
+        match event {
+            AccountEvent::Deposited { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), *amount, Decimal::ZERO);
+                self.ledger.insert(*transaction_id, LedgerEntry { kind: EntryKind::Deposit, amount: *amount, currency: currency.clone() });
+            }
+            AccountEvent::Withdrew { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), -*amount, Decimal::ZERO);
+                self.ledger.insert(*transaction_id, LedgerEntry { kind: EntryKind::Withdrawal, amount: *amount, currency: currency.clone() });
+            }
+            AccountEvent::TransferredOut { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), -*amount, Decimal::ZERO);
+                self.ledger.insert(*transaction_id, LedgerEntry { kind: EntryKind::TransferOut, amount: *amount, currency: currency.clone() });
+            }
+            AccountEvent::TransferredIn { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), *amount, Decimal::ZERO);
+                self.ledger.insert(*transaction_id, LedgerEntry { kind: EntryKind::TransferIn, amount: *amount, currency: currency.clone() });
+            }
+            AccountEvent::Authorized { transaction_id, amount, currency, expires_at, .. } => {
+                self.adjust(currency.as_ref(), -*amount, *amount);
+                self.ledger.insert(*transaction_id, LedgerEntry { kind: EntryKind::Authorization, amount: *amount, currency: currency.clone() });
+                self.holds.insert(*transaction_id, Hold { status: HoldStatus::Open, amount: *amount, currency: currency.clone(), expires_at: *expires_at });
+            }
+            AccountEvent::Captured { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), Decimal::ZERO, -*amount);
+                self.close_hold(*transaction_id, HoldStatus::Captured);
+            }
+            AccountEvent::HoldReleased { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), *amount, -*amount);
+                self.close_hold(*transaction_id, HoldStatus::Released);
+            }
+            AccountEvent::HoldExpired { transaction_id, amount, currency, .. } => {
+                self.adjust(currency.as_ref(), *amount, -*amount);
+                self.close_hold(*transaction_id, HoldStatus::Expired);
+            }
+            AccountEvent::FundsHeld { transaction_id, disputed, amount, currency, .. } => {
+                let available = if *disputed == TransactionType::Deposit { -*amount } else { Decimal::ZERO };
+
+                self.adjust(currency.as_ref(), available, *amount);
+                self.disputes.insert(*transaction_id, DisputeStatus::InProgress);
+            }
+            AccountEvent::FundsReleased { transaction_id, disputed, amount, currency, .. } => {
+                let available = if *disputed == TransactionType::Deposit { *amount } else { Decimal::ZERO };
+
+                self.adjust(currency.as_ref(), available, -*amount);
+                self.disputes.insert(*transaction_id, DisputeStatus::Resolved);
+            }
+            AccountEvent::ChargedBack { transaction_id, disputed, amount, currency, .. } => {
+                let available = if *disputed == TransactionType::Withdrawal { *amount } else { Decimal::ZERO };
+
+                self.adjust(currency.as_ref(), available, -*amount);
+                self.disputes.insert(*transaction_id, DisputeStatus::Chargeback);
+            }
+            AccountEvent::Locked { .. } => {
+                self.locked = true;
+            }
+            AccountEvent::Migrated { state, .. } => {
+                self.available = state.available;
+                self.held = state.held;
+                self.currencies = state.currencies.clone();
+                self.locked = state.locked;
+                self.ledger = state.ledger.iter().map(|(transaction_id, entry)| (*transaction_id, entry.clone())).collect();
+                self.disputes = state.disputes.iter().map(|(transaction_id, status)| (*transaction_id, status.clone())).collect();
+                self.holds = state.holds.iter().map(|(transaction_id, hold)| (*transaction_id, hold.clone())).collect();
+            }
+        }
+
+        let entry = HistoryEntry::new(self.history.last(), event.clone());
+        self.history.push(entry);
+    }
+
+    fn close_hold(&mut self, transaction_id: TransactionId, status: HoldStatus) {
+        if let Some(hold) = self.holds.get_mut(&transaction_id) {
+            hold.status = status;
+        }
+    }
+
+    /// Moves the balance of a currency, the default balance lives in `available` and `held` directly.
+    fn adjust(&mut self, currency: Option<&Currency>, available: Decimal, held: Decimal) {
+        match currency {
+            None => {
+                self.available += available;
+                self.held += held;
+            }
+            Some(currency) => {
+                let balance = self.currencies.entry(currency.clone()).or_default();
+                balance.available += available;
+                balance.held += held;
+            }
+        }
+    }
+
+    /// The amount of a deposit, withdrawal, transfer or authorization, required, positive and within the scale allowed by the money policy.
+    fn get_amount(transaction: &Transaction, policy: &AccountPolicy) -> Result<Decimal, AccountError> {
+        let Some(amount) = transaction.amount else {
+            return Err(AccountError::amount_required(transaction))
+        };
+
+        if amount.is_sign_negative() {
+            return Err(AccountError::negative_amount(transaction))
+        }
+
+        let money = &policy.money;
+
+        money.police(amount)
+            .ok_or_else(|| AccountError::excess_scale(transaction, money.max_scale.unwrap_or_default()))
+    }
+
+    /// Looks up the referenced ledger entry, hiding withdrawals unless the policy allows disputing them.
+    ///
+    /// Transfers are never disputable, reversing them would move funds of two clients, and neither
+    /// are authorizations, which are captured or released instead.
+    fn get_disputable(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<LedgerEntry, AccountError> {
+        self.ledger.get(&transaction.transaction_id).cloned()
+            .filter(|entry| match entry.kind {
+                EntryKind::Deposit => true,
+                EntryKind::Withdrawal => policy.withdrawal_disputes == WithdrawalDisputes::Enabled,
+                EntryKind::TransferOut | EntryKind::TransferIn | EntryKind::Authorization => false
+            })
             .ok_or_else(|| AccountError::transaction_not_found(transaction))
     }
 
+    /// Every amount-bearing transaction is recorded in the ledger, so any reuse of its ID is a redelivery.
+    ///
+    /// Without idempotency a reused ID replaces the ledger entry, unless the entry is under dispute or
+    /// holds funds for an open authorization: settling the replaced amount would leave funds stranded in held.
+    fn check_not_duplicate(&self, transaction: &Transaction, policy: &AccountPolicy) -> Result<(), AccountError> {
+        let duplicate = match policy.idempotency {
+            IdempotencyScope::Off => self.disputes.get(&transaction.transaction_id) == Some(&DisputeStatus::InProgress)
+                || self.holds.get(&transaction.transaction_id).is_some_and(|hold| hold.status == HoldStatus::Open),
+            _ => self.ledger.contains_key(&transaction.transaction_id)
+        };
+
+        if duplicate {
+            return Err(AccountError::duplicate_transaction(transaction))
+        }
+
+        Ok(())
+    }
+
+    /// Looks up the hold of a referenced authorization that is still open at `now`.
+    fn get_open_hold(&self, transaction: &Transaction, now: Option<u64>) -> Result<&Hold, AccountError> {
+        let hold = self.holds.get(&transaction.transaction_id)
+            .ok_or_else(|| AccountError::transaction_not_found(transaction))?;
+
+        if !hold.is_open_at(now) {
+            return Err(AccountError::hold_not_open(transaction))
+        }
+
+        Ok(hold)
+    }
+
+    /// Whether the account is the destination of a transfer rather than its client.
+    fn is_credited_by(&self, transaction: &Transaction) -> bool {
+        transaction.transaction_type == TransactionType::Transfer
+            && transaction.account_id != self.account_id
+            && transaction.destination_id == Some(self.account_id)
+    }
+
     fn check_dispute_in_progress(&self, transaction: &Transaction) -> Result<(), AccountError> {
         let status = self.disputes.get(&transaction.transaction_id)
             .ok_or_else(|| AccountError::dispute_not_found(transaction))?;
@@ -174,3 +636,7 @@ impl Account {
         Ok(())
     }
 }
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,82 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+const MAX_CODE_LENGTH: usize = 12;
+
+/// The code of a currency, e.g. `USD` or `BTC`.
+///
+/// Codes are case insensitive and stored upper case, made of 1 to 12 ASCII letters and digits.
+/// Transactions without a currency move the default balance of an account, which is what every
+/// input had before currencies existed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency(Box<str>);
+
+impl Currency {
+    /// The upper case code.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let code = code.trim();
+
+        if code.is_empty() || code.len() > MAX_CODE_LENGTH || !code.chars().all(|character| character.is_ascii_alphanumeric()) {
+            return Err(anyhow!("Invalid currency '{code}', expected 1 to {MAX_CODE_LENGTH} ASCII letters or digits"))
+        }
+
+        Ok(Self(code.to_ascii_uppercase().into_boxed_str()))
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = Error;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        code.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.0.into_string()
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+/// The funds of an account in a single currency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Balance {
+    pub(crate) available: Decimal,
+    pub(crate) held: Decimal
+}
+
+impl Balance {
+    /// Funds available for withdrawal or trading.
+    pub fn available(&self) -> Decimal {
+        self.available
+    }
+
+    /// Funds held due to active disputes.
+    pub fn held(&self) -> Decimal {
+        self.held
+    }
+
+    /// Calculates the total funds (available + held).
+    pub fn total(&self) -> Decimal {
+        self.available + self.held
+    }
+}
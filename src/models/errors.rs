@@ -1,9 +1,31 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 use thiserror::Error;
 
 use crate::models::{Transaction, TransactionType};
 use crate::types::{AccountId, TransactionId};
 
+/// Broad classification of an [`AccountError`], used to group errors in reports and dashboards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The transaction itself is malformed, regardless of the account state.
+    Validation,
+    /// The transaction conflicts with the current state of the account.
+    State,
+    /// The transaction is refused by an engine rule (locking, idempotency).
+    Policy,
+    /// Applying the transaction would overflow the decimal range.
+    Arithmetic
+}
+
+/// Business rule violations raised while applying a [`Transaction`] to an account.
+///
+/// None of these errors are fatal to the engine; the offending transaction is skipped
+/// and processing continues. New variants may be added in minor releases.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum AccountError {
     #[error("Account is locked for client [{account_id}]")]
     AccountLocked {
@@ -57,6 +79,46 @@ pub enum AccountError {
         transaction_id: TransactionId,
         transaction_type: TransactionType
     },
+    #[error("Amount has more than [{max_scale}] decimals for transaction [{transaction_id}]:[{transaction_type:?}] for client [{account_id}]")]
+    ExcessScale {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType,
+        max_scale: u32
+    },
+    #[error("Transaction [{transaction_id}]:[{transaction_type:?}] for client [{account_id}] references a transaction owned by client [{owner_id}]")]
+    ClientMismatch {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType,
+        owner_id: AccountId
+    },
+    #[error("Hold for transaction [{transaction_id}]:[{transaction_type:?}] is not open for client [{account_id}]")]
+    HoldNotOpen {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType
+    },
+    #[error("Transfer [{transaction_id}]:[{transaction_type:?}] for client [{account_id}] requires a destination other than the client")]
+    InvalidDestination {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType
+    },
+    #[error("Destination client [{destination_id}] of transfer [{transaction_id}]:[{transaction_type:?}] for client [{account_id}] is locked")]
+    DestinationLocked {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType,
+        destination_id: AccountId
+    },
+    #[error("Destination client [{destination_id}] of transfer [{transaction_id}]:[{transaction_type:?}] for client [{account_id}] is unavailable")]
+    DestinationUnavailable {
+        account_id: AccountId,
+        transaction_id: TransactionId,
+        transaction_type: TransactionType,
+        destination_id: AccountId
+    },
     #[error("Numeric overflow occurred for transaction [{transaction_id}]:[{transaction_type:?}] for client [{account_id}]")]
     Overflow {
         account_id: AccountId,
@@ -66,15 +128,113 @@ pub enum AccountError {
 }
 
 impl AccountError {
+    /// Stable, machine-readable code identifying the kind of error.
+    ///
+    /// Codes never change once released, making them safe to persist, count and alert on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AccountLocked { .. } => "E_ACCOUNT_LOCKED",
+            Self::DuplicateTransaction { .. } => "E_DUPLICATE_TRANSACTION",
+            Self::DuplicateDispute { .. } => "E_DUPLICATE_DISPUTE",
+            Self::TransactionNotFound { .. } => "E_TRANSACTION_NOT_FOUND",
+            Self::DisputeNotFound { .. } => "E_DISPUTE_NOT_FOUND",
+            Self::AmountRequired { .. } => "E_AMOUNT_REQUIRED",
+            Self::InsufficientFunds { .. } => "E_INSUFFICIENT_FUNDS",
+            Self::DisputeNotInProgress { .. } => "E_DISPUTE_NOT_IN_PROGRESS",
+            Self::NegativeAmount { .. } => "E_NEGATIVE_AMOUNT",
+            Self::ExcessScale { .. } => "E_EXCESS_SCALE",
+            Self::ClientMismatch { .. } => "E_CLIENT_MISMATCH",
+            Self::HoldNotOpen { .. } => "E_HOLD_NOT_OPEN",
+            Self::InvalidDestination { .. } => "E_INVALID_DESTINATION",
+            Self::DestinationLocked { .. } => "E_DESTINATION_LOCKED",
+            Self::DestinationUnavailable { .. } => "E_DESTINATION_UNAVAILABLE",
+            Self::Overflow { .. } => "E_OVERFLOW"
+        }
+    }
+
+    /// The broad category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::AmountRequired { .. } | Self::NegativeAmount { .. } | Self::ExcessScale { .. } |
+            Self::ClientMismatch { .. } | Self::InvalidDestination { .. } => ErrorCategory::Validation,
+            Self::DuplicateDispute { .. } | Self::TransactionNotFound { .. } | Self::DisputeNotFound { .. } |
+            Self::InsufficientFunds { .. } | Self::DisputeNotInProgress { .. } | Self::HoldNotOpen { .. } |
+            Self::DestinationUnavailable { .. } => ErrorCategory::State,
+            Self::AccountLocked { .. } | Self::DuplicateTransaction { .. } | Self::DestinationLocked { .. } => ErrorCategory::Policy,
+            Self::Overflow { .. } => ErrorCategory::Arithmetic
+        }
+    }
+
+    /// Whether re-submitting the same transaction later could succeed.
+    ///
+    /// This is the case when the error depends on state that later transactions may change,
+    /// e.g. funds arriving after a withdrawal or a deposit arriving after its dispute.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::InsufficientFunds { .. } | Self::TransactionNotFound { .. } | Self::DisputeNotFound { .. } | Self::DestinationUnavailable { .. })
+    }
+
+    /// The client the rejected transaction was addressed to.
+    pub fn account_id(&self) -> AccountId {
+        match self {
+            Self::AccountLocked { account_id } |
+            Self::DuplicateTransaction { account_id, .. } |
+            Self::DuplicateDispute { account_id, .. } |
+            Self::TransactionNotFound { account_id, .. } |
+            Self::DisputeNotFound { account_id, .. } |
+            Self::AmountRequired { account_id, .. } |
+            Self::InsufficientFunds { account_id, .. } |
+            Self::DisputeNotInProgress { account_id, .. } |
+            Self::NegativeAmount { account_id, .. } |
+            Self::ExcessScale { account_id, .. } |
+            Self::ClientMismatch { account_id, .. } |
+            Self::HoldNotOpen { account_id, .. } |
+            Self::InvalidDestination { account_id, .. } |
+            Self::DestinationLocked { account_id, .. } |
+            Self::DestinationUnavailable { account_id, .. } |
+            Self::Overflow { account_id, .. } => *account_id
+        }
+    }
+
+    /// The ID of the rejected transaction, absent for errors that are not tied to one.
+    pub fn transaction_id(&self) -> Option<TransactionId> {
+        self.transaction().map(|(transaction_id, _)| transaction_id)
+    }
+
+    /// The type of the rejected transaction, absent for errors that are not tied to one.
+    pub fn transaction_type(&self) -> Option<TransactionType> {
+        self.transaction().map(|(_, transaction_type)| transaction_type)
+    }
+
+    fn transaction(&self) -> Option<(TransactionId, TransactionType)> {
+        match self {
+            Self::AccountLocked { .. } => None,
+            Self::DuplicateTransaction { transaction_id, transaction_type, .. } |
+            Self::DuplicateDispute { transaction_id, transaction_type, .. } |
+            Self::TransactionNotFound { transaction_id, transaction_type, .. } |
+            Self::DisputeNotFound { transaction_id, transaction_type, .. } |
+            Self::AmountRequired { transaction_id, transaction_type, .. } |
+            Self::InsufficientFunds { transaction_id, transaction_type, .. } |
+            Self::DisputeNotInProgress { transaction_id, transaction_type, .. } |
+            Self::NegativeAmount { transaction_id, transaction_type, .. } |
+            Self::ExcessScale { transaction_id, transaction_type, .. } |
+            Self::ClientMismatch { transaction_id, transaction_type, .. } |
+            Self::HoldNotOpen { transaction_id, transaction_type, .. } |
+            Self::InvalidDestination { transaction_id, transaction_type, .. } |
+            Self::DestinationLocked { transaction_id, transaction_type, .. } |
+            Self::DestinationUnavailable { transaction_id, transaction_type, .. } |
+            Self::Overflow { transaction_id, transaction_type, .. } => Some((*transaction_id, *transaction_type))
+        }
+    }
+
     //NOTE: I know this is not seen in Rust as often but I did not like seeing every error type take the same parameters making
     //      the code highly verbose and more difficult to read.  In my past life using C# I came across many patterns, the factory
     //      patten made sense to make these specific errors easier to instantiate and use.
 
-    pub fn account_locked(transaction: &Transaction) -> Self {
+    pub(crate) fn account_locked(transaction: &Transaction) -> Self {
         Self::AccountLocked { account_id: transaction.account_id }
     }
 
-    pub fn duplicate_transaction(transaction: &Transaction) -> Self {
+    pub(crate) fn duplicate_transaction(transaction: &Transaction) -> Self {
         Self::DuplicateTransaction {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -82,7 +242,7 @@ impl AccountError {
         }
     }
 
-    pub fn duplicate_dispute(transaction: &Transaction) -> Self {
+    pub(crate) fn duplicate_dispute(transaction: &Transaction) -> Self {
         Self::DuplicateDispute {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -90,7 +250,7 @@ impl AccountError {
         }
     }
 
-    pub fn transaction_not_found(transaction: &Transaction) -> Self {
+    pub(crate) fn transaction_not_found(transaction: &Transaction) -> Self {
         Self::TransactionNotFound {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -98,7 +258,7 @@ impl AccountError {
         }
     }
 
-    pub fn dispute_not_found(transaction: &Transaction) -> Self {
+    pub(crate) fn dispute_not_found(transaction: &Transaction) -> Self {
         Self::DisputeNotFound {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -106,7 +266,7 @@ impl AccountError {
         }
     }
 
-    pub fn amount_required(transaction: &Transaction) -> Self {
+    pub(crate) fn amount_required(transaction: &Transaction) -> Self {
         Self::AmountRequired {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -114,7 +274,7 @@ impl AccountError {
         }
     }
 
-    pub fn insufficient_funds(transaction: &Transaction) -> Self {
+    pub(crate) fn insufficient_funds(transaction: &Transaction) -> Self {
         Self::InsufficientFunds {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -122,7 +282,7 @@ impl AccountError {
         }
     }
 
-    pub fn dispute_not_in_progress(transaction: &Transaction) -> Self {
+    pub(crate) fn dispute_not_in_progress(transaction: &Transaction) -> Self {
         Self::DisputeNotInProgress {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -130,7 +290,7 @@ impl AccountError {
         }
     }
 
-    pub fn negative_amount(transaction: &Transaction) -> Self {
+    pub(crate) fn negative_amount(transaction: &Transaction) -> Self {
         Self::NegativeAmount {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -138,7 +298,59 @@ impl AccountError {
         }
     }
 
-    pub fn overflow(transaction: &Transaction) -> Self {
+    pub(crate) fn excess_scale(transaction: &Transaction, max_scale: u32) -> Self {
+        Self::ExcessScale {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+            max_scale
+        }
+    }
+
+    pub(crate) fn client_mismatch(transaction: &Transaction, owner_id: AccountId) -> Self {
+        Self::ClientMismatch {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+            owner_id
+        }
+    }
+
+    pub(crate) fn hold_not_open(transaction: &Transaction) -> Self {
+        Self::HoldNotOpen {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+        }
+    }
+
+    pub(crate) fn invalid_destination(transaction: &Transaction) -> Self {
+        Self::InvalidDestination {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+        }
+    }
+
+    pub(crate) fn destination_locked(transaction: &Transaction, destination_id: AccountId) -> Self {
+        Self::DestinationLocked {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+            destination_id
+        }
+    }
+
+    pub(crate) fn destination_unavailable(transaction: &Transaction, destination_id: AccountId) -> Self {
+        Self::DestinationUnavailable {
+            account_id: transaction.account_id,
+            transaction_id: transaction.transaction_id,
+            transaction_type: transaction.transaction_type,
+            destination_id
+        }
+    }
+
+    pub(crate) fn overflow(transaction: &Transaction) -> Self {
         Self::Overflow {
             account_id: transaction.account_id,
             transaction_id: transaction.transaction_id,
@@ -146,3 +358,45 @@ impl AccountError {
         }
     }
 }
+
+/// Serializes as a flat object carrying the code, category, retryable flag, message and the
+/// transaction it relates to, so errors can be shipped across process boundaries.
+impl Serialize for AccountError {
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        let mut state = serializer.serialize_struct("AccountError", 7)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("category", &self.category())?;
+        state.serialize_field("retryable", &self.is_retryable())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("client", &self.account_id())?;
+        state.serialize_field("tx", &self.transaction_id())?;
+        state.serialize_field("type", &self.transaction_type())?;
+        state.end()
+    }
+}
+
+/// A tampered account history, found by [`Account::verify`](crate::Account::verify).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ChainError {
+    #[error("History of client [{account_id}] is broken at entry [{sequence}] for transaction [{transaction_id}]")]
+    BrokenLink {
+        account_id: AccountId,
+        /// The 0-based position of the first entry whose hash does not match.
+        sequence: usize,
+        transaction_id: TransactionId
+    },
+    #[error("Balances, ledger or disputes of client [{account_id}] do not match its history")]
+    StateMismatch {
+        account_id: AccountId
+    }
+}
+
+impl ChainError {
+    /// The client whose history is broken.
+    pub fn account_id(&self) -> AccountId {
+        match self {
+            Self::BrokenLink { account_id, .. } | Self::StateMismatch { account_id } => *account_id
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Balance, Currency, DisputeStatus, EntryKind, Hold, LedgerEntry, TransactionType};
+use crate::types::{AccountId, TransactionId};
+
+/// A domain event emitted by every successful [`Account::apply`](crate::Account::apply).
+///
+/// Events record what happened to an account rather than its resulting state, folding every event
+/// of a client with [`Account::from_events`](crate::Account::from_events) rebuilds the account exactly.
+/// Disputes carry the type and currency of the disputed transaction, since they decide which balances move.
+///
+/// New variants may be added in minor releases, so matches outside this crate must include a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AccountEvent {
+    /// Funds were credited to the available balance.
+    Deposited {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// Funds were debited from the available balance.
+    Withdrew {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// A transaction was disputed and its amount is held.
+    FundsHeld {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        disputed: TransactionType,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// A dispute was resolved and its held amount released.
+    FundsReleased {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        disputed: TransactionType,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// A dispute ended in a chargeback and its held amount was reversed.
+    ChargedBack {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        disputed: TransactionType,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// Funds were debited from the available balance and credited to another client.
+    TransferredOut {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        #[serde(rename = "to")]
+        destination_id: AccountId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// Funds debited from another client were credited to the available balance.
+    TransferredIn {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        #[serde(rename = "from")]
+        source_id: AccountId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// Funds were moved from the available to the held balance under an authorization hold.
+    Authorized {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>,
+        /// Unix time in seconds at which the hold is released automatically, never when absent.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires_at: Option<u64>
+    },
+    /// An authorization hold was captured and its held amount debited.
+    Captured {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// An authorization hold was released and its held amount made available again.
+    HoldReleased {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// An authorization hold expired before being captured or released, its held amount was made available again.
+    HoldExpired {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId,
+        amount: Decimal,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        currency: Option<Currency>
+    },
+    /// The account was frozen by the transaction.
+    Locked {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        #[serde(rename = "tx")]
+        transaction_id: TransactionId
+    },
+    /// The account was persisted before it had a history, which starts from its state at that point.
+    Migrated {
+        #[serde(rename = "client")]
+        account_id: AccountId,
+        state: Box<MigratedState>
+    }
+}
+
+/// The whole state of an account recorded by [`AccountEvent::Migrated`].
+///
+/// Maps are sorted so the state always hashes the same way.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MigratedState {
+    pub(crate) available: Decimal,
+    pub(crate) held: Decimal,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) currencies: BTreeMap<Currency, Balance>,
+    pub(crate) locked: bool,
+    pub(crate) ledger: BTreeMap<TransactionId, LedgerEntry>,
+    pub(crate) disputes: BTreeMap<TransactionId, DisputeStatus>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) holds: BTreeMap<TransactionId, Hold>
+}
+
+impl AccountEvent {
+    /// The client the event belongs to.
+    pub fn account_id(&self) -> AccountId {
+        match self {
+            Self::Deposited { account_id, .. }
+            | Self::Withdrew { account_id, .. }
+            | Self::FundsHeld { account_id, .. }
+            | Self::FundsReleased { account_id, .. }
+            | Self::ChargedBack { account_id, .. }
+            | Self::TransferredOut { account_id, .. }
+            | Self::TransferredIn { account_id, .. }
+            | Self::Authorized { account_id, .. }
+            | Self::Captured { account_id, .. }
+            | Self::HoldReleased { account_id, .. }
+            | Self::HoldExpired { account_id, .. }
+            | Self::Locked { account_id, .. }
+            | Self::Migrated { account_id, .. } => *account_id
+        }
+    }
+
+    /// The transaction that caused the event, `0` for a migration which no transaction caused.
+    pub fn transaction_id(&self) -> TransactionId {
+        match self {
+            Self::Deposited { transaction_id, .. }
+            | Self::Withdrew { transaction_id, .. }
+            | Self::FundsHeld { transaction_id, .. }
+            | Self::FundsReleased { transaction_id, .. }
+            | Self::ChargedBack { transaction_id, .. }
+            | Self::TransferredOut { transaction_id, .. }
+            | Self::TransferredIn { transaction_id, .. }
+            | Self::Authorized { transaction_id, .. }
+            | Self::Captured { transaction_id, .. }
+            | Self::HoldReleased { transaction_id, .. }
+            | Self::HoldExpired { transaction_id, .. }
+            | Self::Locked { transaction_id, .. } => *transaction_id,
+            Self::Migrated { .. } => 0
+        }
+    }
+
+    /// The currency whose balance moved, absent for the default balance, locks and migrations.
+    pub fn currency(&self) -> Option<&Currency> {
+        match self {
+            Self::Deposited { currency, .. }
+            | Self::Withdrew { currency, .. }
+            | Self::FundsHeld { currency, .. }
+            | Self::FundsReleased { currency, .. }
+            | Self::ChargedBack { currency, .. }
+            | Self::TransferredOut { currency, .. }
+            | Self::TransferredIn { currency, .. }
+            | Self::Authorized { currency, .. }
+            | Self::Captured { currency, .. }
+            | Self::HoldReleased { currency, .. }
+            | Self::HoldExpired { currency, .. } => currency.as_ref(),
+            Self::Locked { .. } | Self::Migrated { .. } => None
+        }
+    }
+}
+
+impl From<EntryKind> for TransactionType {
+    fn from(kind: EntryKind) -> Self {
+        match kind {
+            EntryKind::Deposit => Self::Deposit,
+            EntryKind::Withdrawal => Self::Withdrawal,
+            EntryKind::TransferOut | EntryKind::TransferIn => Self::Transfer,
+            EntryKind::Authorization => Self::Authorize
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::{AccountEvent, DisputeStatus, EntryKind, HoldStatus, MigratedState, TransactionType};
+
+/// A single link of the tamper-evident history of an account.
+///
+/// Every event folded into an account is appended to its history together with a SHA-256 hash
+/// over the hash of the previous link and the event itself, so altering, removing or reordering
+/// any entry breaks every hash after it. See [`Account::verify`](crate::Account::verify).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub(crate) event: AccountEvent,
+    pub(crate) hash: String
+}
+
+impl HistoryEntry {
+    /// Links `event` to the entry before it, if any.
+    pub(crate) fn new(previous: Option<&HistoryEntry>, event: AccountEvent) -> Self {
+        let hash = link_hash(previous.map_or("", |entry| entry.hash.as_str()), &event);
+        Self { event, hash }
+    }
+
+    /// The event recorded by this entry.
+    pub fn event(&self) -> &AccountEvent {
+        &self.event
+    }
+
+    /// The hex encoded hash linking this entry to the previous one.
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// Hashes the previous link together with a canonical encoding of the event.
+///
+/// The encoding is spelled out rather than derived from serde or `Debug`, so persisted hashes
+/// stay valid when the serialized form of an event changes.
+pub(crate) fn link_hash(previous: &str, event: &AccountEvent) -> String {
+    let mut canonical = match event {
+        AccountEvent::Deposited { account_id, transaction_id, amount, .. } => format!("deposited|{account_id}|{transaction_id}|{amount}"),
+        AccountEvent::Withdrew { account_id, transaction_id, amount, .. } => format!("withdrew|{account_id}|{transaction_id}|{amount}"),
+        AccountEvent::FundsHeld { account_id, transaction_id, disputed, amount, .. } => {
+            format!("funds_held|{account_id}|{transaction_id}|{}|{amount}", type_name(*disputed))
+        }
+        AccountEvent::FundsReleased { account_id, transaction_id, disputed, amount, .. } => {
+            format!("funds_released|{account_id}|{transaction_id}|{}|{amount}", type_name(*disputed))
+        }
+        AccountEvent::ChargedBack { account_id, transaction_id, disputed, amount, .. } => {
+            format!("charged_back|{account_id}|{transaction_id}|{}|{amount}", type_name(*disputed))
+        }
+        AccountEvent::TransferredOut { account_id, transaction_id, destination_id, amount, .. } => {
+            format!("transferred_out|{account_id}|{transaction_id}|{destination_id}|{amount}")
+        }
+        AccountEvent::TransferredIn { account_id, transaction_id, source_id, amount, .. } => {
+            format!("transferred_in|{account_id}|{transaction_id}|{source_id}|{amount}")
+        }
+        AccountEvent::Authorized { account_id, transaction_id, amount, expires_at, .. } => {
+            format!("authorized|{account_id}|{transaction_id}|{amount}|{}", expires_at.map_or_else(String::new, |expires_at| expires_at.to_string()))
+        }
+        AccountEvent::Captured { account_id, transaction_id, amount, .. } => format!("captured|{account_id}|{transaction_id}|{amount}"),
+        AccountEvent::HoldReleased { account_id, transaction_id, amount, .. } => format!("hold_released|{account_id}|{transaction_id}|{amount}"),
+        AccountEvent::HoldExpired { account_id, transaction_id, amount, .. } => format!("hold_expired|{account_id}|{transaction_id}|{amount}"),
+        AccountEvent::Locked { account_id, transaction_id } => format!("locked|{account_id}|{transaction_id}"),
+        AccountEvent::Migrated { account_id, state } => format!("migrated|{account_id}|{}", migrated_state(state))
+    };
+
+    //NOTE: Only appended when present, so the hashes of events in the default balance are the same as before currencies existed
+    if let Some(currency) = event.currency() {
+        canonical.push('|');
+        canonical.push_str(currency.as_str());
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(previous.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(canonical.as_bytes());
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Encodes every balance, ledger entry, dispute and hold of a migrated state, in key order.
+fn migrated_state(state: &MigratedState) -> String {
+    let mut parts = vec![state.available.to_string(), state.held.to_string(), state.locked.to_string()];
+
+    for (currency, balance) in &state.currencies {
+        parts.push(format!("balance:{}:{}:{}", currency.as_str(), balance.available, balance.held));
+    }
+
+    for (transaction_id, entry) in &state.ledger {
+        let currency = entry.currency.as_ref().map_or("", |currency| currency.as_str());
+        parts.push(format!("ledger:{transaction_id}:{}:{}:{currency}", entry_kind_name(entry.kind), entry.amount));
+    }
+
+    for (transaction_id, status) in &state.disputes {
+        parts.push(format!("dispute:{transaction_id}:{}", dispute_status_name(status)));
+    }
+
+    for (transaction_id, hold) in &state.holds {
+        let currency = hold.currency.as_ref().map_or("", |currency| currency.as_str());
+        let expires_at = hold.expires_at.map_or_else(String::new, |expires_at| expires_at.to_string());
+        parts.push(format!("hold:{transaction_id}:{}:{}:{currency}:{expires_at}", hold_status_name(hold.status), hold.amount));
+    }
+
+    parts.join("|")
+}
+
+fn entry_kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Deposit => "deposit",
+        EntryKind::Withdrawal => "withdrawal",
+        EntryKind::TransferOut => "transfer_out",
+        EntryKind::TransferIn => "transfer_in",
+        EntryKind::Authorization => "authorization"
+    }
+}
+
+fn dispute_status_name(status: &DisputeStatus) -> &'static str {
+    match status {
+        DisputeStatus::InProgress => "in_progress",
+        DisputeStatus::Resolved => "resolved",
+        DisputeStatus::Chargeback => "chargeback"
+    }
+}
+
+fn hold_status_name(status: HoldStatus) -> &'static str {
+    match status {
+        HoldStatus::Open => "open",
+        HoldStatus::Captured => "captured",
+        HoldStatus::Released => "released",
+        HoldStatus::Expired => "expired"
+    }
+}
+
+fn type_name(transaction_type: TransactionType) -> &'static str {
+    match transaction_type {
+        TransactionType::Deposit => "deposit",
+        TransactionType::Withdrawal => "withdrawal",
+        TransactionType::Dispute => "dispute",
+        TransactionType::Resolve => "resolve",
+        TransactionType::Chargeback => "chargeback",
+        TransactionType::Transfer => "transfer",
+        TransactionType::Authorize => "authorize",
+        TransactionType::Capture => "capture",
+        TransactionType::Release => "release"
+    }
+}
\ No newline at end of file
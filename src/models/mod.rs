@@ -1,27 +1,104 @@
 mod account;
+mod currency;
 mod errors;
+mod event;
+mod history;
+mod money;
+mod policy;
 #[cfg(test)]
 mod tests;
 mod transaction;
 
-use serde::Deserialize;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
 pub use account::Account;
+pub use currency::{Balance, Currency};
+pub use errors::{AccountError, ChainError, ErrorCategory};
+pub use event::{AccountEvent, MigratedState};
+pub use history::HistoryEntry;
+pub use money::{ExcessScale, MoneyPolicy, RoundingMode};
+pub use policy::{AccountPolicy, IdempotencyScope, WithdrawalDisputes};
 pub use transaction::Transaction;
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+/// The kind of operation a [`Transaction`] performs against an account.
+///
+/// New variants may be added in minor releases, so matches outside this crate
+/// must include a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
+#[non_exhaustive]
 pub enum TransactionType {
     Deposit,
     Withdrawal,
     Dispute,
     Resolve,
-    Chargeback
+    Chargeback,
+    Transfer,
+    Authorize,
+    Capture,
+    Release
+}
+
+impl TransactionType {
+    /// Whether the transaction moves funds and is therefore recorded in the ledger under its own ID.
+    pub(crate) fn is_amount_bearing(&self) -> bool {
+        matches!(self, Self::Deposit | Self::Withdrawal | Self::Transfer | Self::Authorize)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EntryKind {
+    Deposit,
+    Withdrawal,
+    TransferOut,
+    TransferIn,
+    Authorization
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct LedgerEntry {
+    pub(crate) kind: EntryKind,
+    pub(crate) amount: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) currency: Option<Currency>
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum DisputeStatus {
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DisputeStatus {
     InProgress,
     Resolved,
     Chargeback
 }
+
+/// An authorization hold, keyed by the ID of the authorization in the ledger.
+///
+/// The hold keeps its own amount, the ledger entry of its ID can be replaced when idempotency is off.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub(crate) struct Hold {
+    pub(crate) status: HoldStatus,
+    pub(crate) amount: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) currency: Option<Currency>,
+    /// Unix time in seconds at which an open hold is released automatically, never when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) expires_at: Option<u64>
+}
+
+impl Hold {
+    /// Whether the hold can still be captured or released at `now`, in Unix seconds, ignoring its expiry without a time.
+    pub(crate) fn is_open_at(&self, now: Option<u64>) -> bool {
+        self.status == HoldStatus::Open && self.expires_at.zip(now).is_none_or(|(expires_at, now)| now < expires_at)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HoldStatus {
+    Open,
+    Captured,
+    Released,
+    Expired
+}
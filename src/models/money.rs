@@ -0,0 +1,136 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// How amounts are read, stored and written by every account of an engine.
+///
+/// Input amounts with more decimals than `max_scale` are rejected or rounded before they reach
+/// the ledger, so balances, disputes and events only ever carry the policed amount. Balances are
+/// written with exactly `output_scale` decimals. Both use the same rounding mode.
+///
+/// The default policy matches the behaviour of the engine before money was configurable: inputs
+/// are accepted at any scale, and outputs are truncated to four decimals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MoneyPolicy {
+    /// The maximum number of decimals of an input amount, unlimited when absent.
+    pub max_scale: Option<u32>,
+    /// What happens to input amounts with more decimals than `max_scale`.
+    pub excess_scale: ExcessScale,
+    /// How amounts are rounded, both on input and on output.
+    pub rounding: RoundingMode,
+    /// The number of decimals every balance is written with.
+    pub output_scale: u32
+}
+
+impl Default for MoneyPolicy {
+    fn default() -> Self {
+        Self {
+            max_scale: None,
+            excess_scale: ExcessScale::default(),
+            rounding: RoundingMode::default(),
+            output_scale: 4
+        }
+    }
+}
+
+impl MoneyPolicy {
+    /// Limits input amounts to `max_scale` decimals, capped at the 28 decimals a [`Decimal`] can hold.
+    pub fn with_max_scale(mut self, max_scale: u32, excess_scale: ExcessScale) -> Self {
+        self.max_scale = Some(max_scale.min(Decimal::MAX_SCALE));
+        self.excess_scale = excess_scale;
+        self
+    }
+
+    /// Sets how amounts are rounded.
+    pub fn with_rounding(mut self, rounding: RoundingMode) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Sets the number of decimals balances are written with, capped at 28.
+    pub fn with_output_scale(mut self, output_scale: u32) -> Self {
+        self.output_scale = output_scale.min(Decimal::MAX_SCALE);
+        self
+    }
+
+    /// Applies the input scale limit, returning `None` when the amount must be rejected.
+    ///
+    /// Trailing zeros do not count, `1.50000` is within a limit of two decimals.
+    pub(crate) fn police(&self, amount: Decimal) -> Option<Decimal> {
+        match self.max_scale {
+            Some(max_scale) if amount.normalize().scale() > max_scale => match self.excess_scale {
+                ExcessScale::Reject => None,
+                ExcessScale::Round => Some(amount.round_dp_with_strategy(max_scale, self.rounding.strategy()))
+            },
+            _ => Some(amount)
+        }
+    }
+
+    /// Rounds a balance to exactly `output_scale` decimals, padding with zeros, ready to be displayed.
+    pub fn round_output(&self, amount: Decimal) -> Decimal {
+        let mut rounded = amount.round_dp_with_strategy(self.output_scale, self.rounding.strategy());
+        rounded.rescale(self.output_scale);
+        rounded
+    }
+}
+
+/// What happens to an input amount with more decimals than the policy allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExcessScale {
+    /// The transaction is rejected with `E_EXCESS_SCALE`.
+    #[default]
+    Reject,
+    /// The amount is rounded to the maximum scale and applied.
+    Round
+}
+
+impl FromStr for ExcessScale {
+    type Err = Error;
+
+    fn from_str(excess_scale: &str) -> Result<Self, Self::Err> {
+        match excess_scale.to_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "round" => Ok(Self::Round),
+            _ => Err(anyhow!("Invalid excess scale handling '{excess_scale}', expected 'reject' or 'round'"))
+        }
+    }
+}
+
+/// How amounts are rounded to a number of decimals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RoundingMode {
+    /// Half to even, e.g. `0.125` and `0.135` round to `0.12` and `0.14`.
+    Bankers,
+    /// Half away from zero, e.g. `0.125` rounds to `0.13` and `-0.125` to `-0.13`.
+    HalfUp,
+    /// Towards zero, dropping the excess decimals.
+    #[default]
+    Truncate
+}
+
+impl RoundingMode {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Self::Bankers => RoundingStrategy::MidpointNearestEven,
+            Self::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Self::Truncate => RoundingStrategy::ToZero
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = Error;
+
+    fn from_str(rounding: &str) -> Result<Self, Self::Err> {
+        match rounding.to_lowercase().as_str() {
+            "bankers" => Ok(Self::Bankers),
+            "half-up" => Ok(Self::HalfUp),
+            "truncate" => Ok(Self::Truncate),
+            _ => Err(anyhow!("Invalid rounding mode '{rounding}', expected 'bankers', 'half-up' or 'truncate'"))
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+
+use crate::models::MoneyPolicy;
+
+/// Business rules applied by every account of an engine.
+///
+/// The default policy matches the behaviour of the engine before any rule was configurable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AccountPolicy {
+    /// Whether disputes may reference withdrawals, and not only deposits.
+    pub withdrawal_disputes: WithdrawalDisputes,
+    /// Where transaction IDs of amount-bearing transactions must be unique.
+    pub idempotency: IdempotencyScope,
+    /// Scale and rounding of input amounts and output balances.
+    pub money: MoneyPolicy,
+    /// How long an authorization holds its funds before it is released automatically, forever when absent.
+    pub hold_expiry: Option<Duration>
+}
+
+impl AccountPolicy {
+    /// Sets where transaction IDs must be unique.
+    pub fn with_idempotency(mut self, idempotency: IdempotencyScope) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
+    /// Sets the scale and rounding of amounts.
+    pub fn with_money(mut self, money: MoneyPolicy) -> Self {
+        self.money = money;
+        self
+    }
+
+    /// Releases authorization holds automatically once they are older than `expiry`, in whole seconds.
+    pub fn with_hold_expiry(mut self, expiry: Duration) -> Self {
+        self.hold_expiry = Some(expiry);
+        self
+    }
+
+    /// Sets whether withdrawals can be disputed.
+    pub fn with_withdrawal_disputes(mut self, withdrawal_disputes: WithdrawalDisputes) -> Self {
+        self.withdrawal_disputes = withdrawal_disputes;
+        self
+    }
+}
+
+/// How disputes referencing a withdrawal are handled.
+///
+/// A disputed withdrawal is a debit the client claims was invalid:
+///
+/// | Step       | `available`   | `held`        |
+/// |------------|---------------|---------------|
+/// | dispute    | unchanged     | `+ amount`    |
+/// | resolve    | unchanged     | `- amount`    |
+/// | chargeback | `+ amount`    | `- amount`    |
+///
+/// The disputed amount is held as a provisional credit while under investigation. A resolve
+/// upholds the withdrawal and drops the credit, while a chargeback reverses the withdrawal,
+/// crediting the amount back to `available` and locking the account like any other chargeback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WithdrawalDisputes {
+    /// Only deposits can be disputed, disputes of withdrawals fail with `TransactionNotFound`.
+    #[default]
+    Disabled,
+    /// Withdrawals can be disputed, resolved and charged back.
+    Enabled
+}
+
+/// Where the IDs of amount-bearing transactions (deposits, withdrawals, transfers and authorizations) must be unique.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdempotencyScope {
+    /// No duplicate detection, a reused ID is applied again and replaces the ledger entry unless
+    /// that entry is under dispute.
+    Off,
+    /// IDs must be unique within each account's ledger.
+    #[default]
+    Account,
+    /// IDs must be unique across all accounts, enforced by an engine-wide index in addition
+    /// to the per-account check.
+    Global
+}
+
+impl FromStr for IdempotencyScope {
+    type Err = Error;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        match scope.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "account" => Ok(Self::Account),
+            "global" => Ok(Self::Global),
+            _ => Err(anyhow!("Invalid idempotency scope '{scope}', expected 'off', 'account' or 'global'"))
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use super::{Account, Transaction, TransactionType};
+use super::{Account, AccountEvent, AccountPolicy, Currency, ExcessScale, IdempotencyScope, MoneyPolicy, RoundingMode, Transaction, TransactionType, WithdrawalDisputes};
 
 use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
 
 use anyhow::Result;
 use rust_decimal::Decimal;
 
-use crate::models::errors::AccountError;
+use crate::models::errors::{AccountError, ChainError, ErrorCategory};
 use crate::types::{AccountId, TransactionId};
 
 fn create_transaction(transaction_type: TransactionType, transaction_id: TransactionId, account_id: AccountId, amount: Option<&str>) -> Result<Transaction> {
@@ -16,7 +17,9 @@ fn create_transaction(transaction_type: TransactionType, transaction_id: Transac
         amount: match amount {
             Some(s) => Some(Decimal::from_str(s)?),
             None => None
-        }
+        },
+        currency: None,
+        destination_id: None
     })
 }
 
@@ -184,3 +187,461 @@ fn test_disputing_a_withdrawal_fails() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_errors_expose_stable_codes_and_categories() -> Result<()> {
+    let mut account = Account::new(1);
+    let withdrawal = create_transaction(TransactionType::Withdrawal, 1, 1, Some("10.0"))?;
+    let error = account.apply(&withdrawal).unwrap_err();
+
+    assert_eq!(error.code(), "E_INSUFFICIENT_FUNDS");
+    assert_eq!(error.category(), ErrorCategory::State);
+    assert!(error.is_retryable());
+
+    account.locked = true;
+    let error = account.apply(&withdrawal).unwrap_err();
+
+    assert_eq!(error.code(), "E_ACCOUNT_LOCKED");
+    assert_eq!(error.category(), ErrorCategory::Policy);
+    assert!(!error.is_retryable());
+    assert_eq!(error.transaction_id(), None);
+
+    Ok(())
+}
+
+#[test]
+fn test_errors_serialize_with_code_and_transaction() -> Result<()> {
+    let mut account = Account::new(3);
+    let deposit = create_transaction(TransactionType::Deposit, 7, 3, Some("-1.0"))?;
+    let error = account.apply(&deposit).unwrap_err();
+
+    let value = serde_json::to_value(&error)?;
+
+    assert_eq!(value["code"], "E_NEGATIVE_AMOUNT");
+    assert_eq!(value["category"], "validation");
+    assert_eq!(value["retryable"], false);
+    assert_eq!(value["client"], 3);
+    assert_eq!(value["tx"], 7);
+    assert_eq!(value["type"], "deposit");
+    assert_eq!(value["message"], error.to_string());
+
+    Ok(())
+}
+
+#[test]
+fn test_disputed_withdrawal_holds_a_provisional_credit_until_resolved() -> Result<()> {
+    let policy = AccountPolicy::default().with_withdrawal_disputes(WithdrawalDisputes::Enabled);
+    let mut account = Account::new(1);
+    account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Withdrawal, 2, 1, Some("40.0"))?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Dispute, 2, 1, None)?, &policy)?;
+
+    assert_eq!(account.available, Decimal::from_str("60.0")?);
+    assert_eq!(account.held, Decimal::from_str("40.0")?);
+
+    account.apply_with(&create_transaction(TransactionType::Resolve, 2, 1, None)?, &policy)?;
+
+    assert_eq!(account.available, Decimal::from_str("60.0")?);
+    assert!(account.held.is_zero());
+    assert!(!account.locked);
+
+    Ok(())
+}
+
+#[test]
+fn test_charged_back_withdrawal_credits_the_amount_and_locks() -> Result<()> {
+    let policy = AccountPolicy::default().with_withdrawal_disputes(WithdrawalDisputes::Enabled);
+    let mut account = Account::new(1);
+    account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Withdrawal, 2, 1, Some("40.0"))?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Dispute, 2, 1, None)?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Chargeback, 2, 1, None)?, &policy)?;
+
+    assert_eq!(account.available, Decimal::from_str("100.0")?);
+    assert!(account.held.is_zero());
+    assert!(account.locked);
+
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_withdrawal_fails_idempotency() -> Result<()> {
+    let mut account = Account::new(1);
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?)?;
+
+    let withdrawal = create_transaction(TransactionType::Withdrawal, 2, 1, Some("30.0"))?;
+    account.apply(&withdrawal)?;
+
+    let result = account.apply(&withdrawal);
+
+    assert!(matches!(result, Err(AccountError::DuplicateTransaction { .. })));
+    assert_eq!(account.available, Decimal::from_str("70.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_withdrawal_reusing_a_deposit_id_fails_idempotency() -> Result<()> {
+    let mut account = Account::new(1);
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?)?;
+
+    let result = account.apply(&create_transaction(TransactionType::Withdrawal, 1, 1, Some("30.0"))?);
+
+    assert!(matches!(result, Err(AccountError::DuplicateTransaction { .. })));
+    assert_eq!(account.available, Decimal::from_str("100.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_rejected_withdrawal_can_be_redelivered() -> Result<()> {
+    let mut account = Account::new(1);
+    let withdrawal = create_transaction(TransactionType::Withdrawal, 2, 1, Some("30.0"))?;
+
+    assert!(matches!(account.apply(&withdrawal), Err(AccountError::InsufficientFunds { .. })));
+
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?)?;
+    account.apply(&withdrawal)?;
+
+    assert_eq!(account.available, Decimal::from_str("70.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_disabled_idempotency_applies_reused_ids_again() -> Result<()> {
+    let policy = AccountPolicy::default().with_idempotency(IdempotencyScope::Off);
+    let mut account = Account::new(1);
+    let deposit = create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?;
+
+    account.apply_with(&deposit, &policy)?;
+    account.apply_with(&deposit, &policy)?;
+
+    assert_eq!(account.available, Decimal::from_str("20.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_disabled_idempotency_never_replaces_a_disputed_entry() -> Result<()> {
+    let policy = AccountPolicy::default().with_idempotency(IdempotencyScope::Off);
+    let mut account = Account::new(1);
+
+    account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?, &policy)?;
+    account.apply_with(&create_transaction(TransactionType::Dispute, 1, 1, None)?, &policy)?;
+
+    let redelivery = account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("5.0"))?, &policy);
+
+    assert!(matches!(redelivery, Err(AccountError::DuplicateTransaction { .. })));
+
+    account.apply_with(&create_transaction(TransactionType::Resolve, 1, 1, None)?, &policy)?;
+
+    assert_eq!(account.available, Decimal::from_str("10.0")?);
+    assert_eq!(account.held, Decimal::ZERO);
+
+    Ok(())
+}
+
+#[test]
+fn test_apply_emits_events_that_rebuild_the_account() -> Result<()> {
+    let policy = AccountPolicy::default().with_withdrawal_disputes(WithdrawalDisputes::Enabled);
+    let mut account = Account::new(1);
+    let mut events = Vec::new();
+
+    events.extend(account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?, &policy)?);
+    events.extend(account.apply_with(&create_transaction(TransactionType::Withdrawal, 2, 1, Some("40.0"))?, &policy)?);
+    events.extend(account.apply_with(&create_transaction(TransactionType::Dispute, 1, 1, None)?, &policy)?);
+    events.extend(account.apply_with(&create_transaction(TransactionType::Resolve, 1, 1, None)?, &policy)?);
+    events.extend(account.apply_with(&create_transaction(TransactionType::Dispute, 2, 1, None)?, &policy)?);
+
+    assert!(account.apply_with(&create_transaction(TransactionType::Withdrawal, 3, 1, Some("500.0"))?, &policy).is_err());
+
+    let chargeback = account.apply_with(&create_transaction(TransactionType::Chargeback, 2, 1, None)?, &policy)?;
+
+    assert_eq!(chargeback, vec![
+        AccountEvent::ChargedBack { account_id: 1, transaction_id: 2, disputed: TransactionType::Withdrawal, amount: Decimal::from_str("40.0")?, currency: None },
+        AccountEvent::Locked { account_id: 1, transaction_id: 2 }
+    ]);
+
+    events.extend(chargeback);
+
+    assert_eq!(events.len(), 7);
+    assert_eq!(events[0], AccountEvent::Deposited { account_id: 1, transaction_id: 1, amount: Decimal::from_str("100.0")?, currency: None });
+    assert_eq!(Account::from_events(1, &events), account);
+
+    // Events of other clients are skipped when folding
+    assert_eq!(Account::from_events(2, &events), Account::new(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_detects_tampered_history_and_state() -> Result<()> {
+    let mut account = Account::new(1);
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("100.0"))?)?;
+    account.apply(&create_transaction(TransactionType::Withdrawal, 2, 1, Some("40.0"))?)?;
+    account.apply(&create_transaction(TransactionType::Dispute, 1, 1, None)?)?;
+
+    assert!(account.verify().is_ok());
+    assert_ne!(account.history()[0].hash(), account.history()[1].hash());
+
+    let mut tampered = account.clone();
+    tampered.history[1].event = AccountEvent::Withdrew { account_id: 1, transaction_id: 2, amount: Decimal::from_str("4.0")?, currency: None };
+
+    assert_eq!(tampered.verify(), Err(ChainError::BrokenLink { account_id: 1, sequence: 1, transaction_id: 2 }));
+
+    let mut tampered = account.clone();
+    tampered.history.remove(0);
+
+    assert!(matches!(tampered.verify(), Err(ChainError::BrokenLink { sequence: 0, .. })));
+
+    let mut tampered = account.clone();
+    tampered.available = Decimal::from_str("1000.0")?;
+
+    assert_eq!(tampered.verify(), Err(ChainError::StateMismatch { account_id: 1 }));
+
+    Ok(())
+}
+
+#[test]
+fn test_money_policy_rejects_or_rounds_excess_scale() -> Result<()> {
+    let rejecting = AccountPolicy::default().with_money(MoneyPolicy::default().with_max_scale(2, ExcessScale::Reject));
+    let mut account = Account::new(1);
+
+    let result = account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("1.005"))?, &rejecting);
+
+    assert!(matches!(result, Err(AccountError::ExcessScale { max_scale: 2, .. })));
+    assert_eq!(result.map_err(|error| error.code()), Err("E_EXCESS_SCALE"));
+
+    account.apply_with(&create_transaction(TransactionType::Deposit, 2, 1, Some("1.50000"))?, &rejecting)?;
+
+    assert_eq!(account.available(), Decimal::from_str("1.5")?);
+
+    let rounding = AccountPolicy::default().with_money(
+        MoneyPolicy::default()
+            .with_max_scale(2, ExcessScale::Round)
+            .with_rounding(RoundingMode::HalfUp)
+    );
+
+    account.apply_with(&create_transaction(TransactionType::Deposit, 3, 1, Some("1.005"))?, &rounding)?;
+    account.apply_with(&create_transaction(TransactionType::Dispute, 3, 1, None)?, &rounding)?;
+
+    let rounded = Decimal::from_str("1.01")?;
+
+    assert_eq!(account.held(), rounded);
+    assert!(account.history().iter().any(|entry| *entry.event() == AccountEvent::Deposited { account_id: 1, transaction_id: 3, amount: rounded, currency: None }));
+
+    Ok(())
+}
+
+#[test]
+fn test_money_policy_rounds_output_to_a_fixed_scale() -> Result<()> {
+    let amount = Decimal::from_str("0.125")?;
+    let output = |rounding: RoundingMode, amount: Decimal| MoneyPolicy::default().with_rounding(rounding).with_output_scale(2).round_output(amount).to_string();
+
+    assert_eq!(output(RoundingMode::Bankers, amount), "0.12");
+    assert_eq!(output(RoundingMode::Bankers, Decimal::from_str("0.135")?), "0.14");
+    assert_eq!(output(RoundingMode::HalfUp, amount), "0.13");
+    assert_eq!(output(RoundingMode::HalfUp, -amount), "-0.13");
+    assert_eq!(output(RoundingMode::Truncate, Decimal::from_str("0.129")?), "0.12");
+    assert_eq!(MoneyPolicy::default().round_output(Decimal::from(25)).to_string(), "25.0000");
+    assert_eq!(MoneyPolicy::default().round_output(Decimal::from_str("1.00009")?).to_string(), "1.0000");
+    assert_eq!(RoundingMode::from_str("half-up")?, RoundingMode::HalfUp);
+    assert!(RoundingMode::from_str("ceiling").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_currencies_keep_separate_balances() -> Result<()> {
+    let usd = Currency::from_str(" usd ")?;
+    let btc = Currency::from_str("BTC")?;
+    let mut account = Account::new(1);
+
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?)?;
+    account.apply(&create_transaction(TransactionType::Deposit, 2, 1, Some("5.0"))?.with_currency(usd.clone()))?;
+
+    let result = account.apply(&create_transaction(TransactionType::Withdrawal, 3, 1, Some("6.0"))?.with_currency(usd.clone()));
+
+    assert!(matches!(result, Err(AccountError::InsufficientFunds { .. })));
+
+    account.apply(&create_transaction(TransactionType::Withdrawal, 4, 1, Some("2.0"))?.with_currency(usd.clone()))?;
+
+    assert_eq!(usd.as_str(), "USD");
+    assert_eq!(account.available(), Decimal::from_str("10.0")?);
+    assert_eq!(account.balance(Some(&usd)).available(), Decimal::from_str("3.0")?);
+    assert_eq!(account.balance(Some(&btc)).total(), Decimal::ZERO);
+    assert_eq!(account.balances().map(|(currency, _)| currency.cloned()).collect::<Vec<_>>(), vec![None, Some(usd)]);
+    assert!(account.verify().is_ok());
+    assert!(Currency::from_str("US D").is_err());
+    assert!(Currency::from_str("").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_disputes_act_on_the_currency_of_the_referenced_transaction() -> Result<()> {
+    let eur = Currency::from_str("EUR")?;
+    let mut account = Account::new(1);
+
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?)?;
+    account.apply(&create_transaction(TransactionType::Deposit, 2, 1, Some("4.0"))?.with_currency(eur.clone()))?;
+
+    let events = account.apply(&create_transaction(TransactionType::Dispute, 2, 1, None)?)?;
+
+    assert_eq!(events[0].currency(), Some(&eur));
+    assert_eq!(account.balance(Some(&eur)).held(), Decimal::from_str("4.0")?);
+    assert_eq!(account.balance(Some(&eur)).available(), Decimal::ZERO);
+    assert_eq!(account.held(), Decimal::ZERO);
+
+    account.apply(&create_transaction(TransactionType::Chargeback, 2, 1, None)?)?;
+
+    assert_eq!(account.balance(Some(&eur)).total(), Decimal::ZERO);
+    assert_eq!(account.total(), Decimal::from_str("10.0")?);
+    assert!(account.is_locked());
+    assert!(account.apply(&create_transaction(TransactionType::Deposit, 3, 1, Some("1.0"))?).is_err());
+    assert_eq!(Account::from_events(1, account.history().iter().map(|entry| entry.event())), account);
+
+    Ok(())
+}
+#[test]
+fn test_transfer_debits_the_client_and_credits_the_destination() -> Result<()> {
+    let mut source = Account::new(1);
+    let mut destination = Account::new(2);
+    let transfer = Transaction::transfer(1, 2, 2, Decimal::from_str("4.0")?);
+
+    source.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?)?;
+
+    let debited = source.apply(&transfer)?;
+    let credited = destination.apply(&transfer)?;
+
+    assert_eq!(debited, vec![AccountEvent::TransferredOut { account_id: 1, transaction_id: 2, destination_id: 2, amount: Decimal::from_str("4.0")?, currency: None }]);
+    assert_eq!(credited, vec![AccountEvent::TransferredIn { account_id: 2, transaction_id: 2, source_id: 1, amount: Decimal::from_str("4.0")?, currency: None }]);
+    assert_eq!(source.available(), Decimal::from_str("6.0")?);
+    assert_eq!(destination.available(), Decimal::from_str("4.0")?);
+    assert_eq!(Account::from_events(2, &credited), destination);
+    assert!(source.verify().is_ok());
+
+    // Redelivery is refused on both sides, and transfers cannot be disputed
+    assert!(matches!(source.apply(&transfer), Err(AccountError::DuplicateTransaction { .. })));
+    assert!(matches!(destination.apply(&transfer), Err(AccountError::DuplicateTransaction { .. })));
+    assert!(matches!(destination.apply(&create_transaction(TransactionType::Dispute, 2, 2, None)?), Err(AccountError::TransactionNotFound { .. })));
+
+    Ok(())
+}
+
+#[test]
+fn test_transfer_is_rejected_without_a_valid_destination_or_funds() -> Result<()> {
+    let mut account = Account::new(1);
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?)?;
+
+    let missing = create_transaction(TransactionType::Transfer, 2, 1, Some("1.0"))?;
+    let to_self = Transaction::transfer(1, 1, 3, Decimal::ONE);
+    let too_much = Transaction::transfer(1, 2, 4, Decimal::from(11));
+
+    assert_eq!(account.apply(&missing).map_err(|error| error.code()), Err("E_INVALID_DESTINATION"));
+    assert_eq!(account.apply(&to_self).map_err(|error| error.code()), Err("E_INVALID_DESTINATION"));
+    assert_eq!(account.apply(&too_much).map_err(|error| error.code()), Err("E_INSUFFICIENT_FUNDS"));
+
+    let mut locked = Account::new(2);
+    locked.locked = true;
+
+    let error = locked.apply(&Transaction::transfer(1, 2, 5, Decimal::ONE)).unwrap_err();
+
+    assert!(matches!(error, AccountError::DestinationLocked { account_id: 1, destination_id: 2, .. }));
+    assert_eq!(error.category(), ErrorCategory::Policy);
+    assert_eq!(account.available(), Decimal::from_str("10.0")?);
+
+    Ok(())
+}
+
+#[test]
+fn test_authorization_holds_funds_until_captured_or_released() -> Result<()> {
+    let mut account = Account::new(1);
+    account.apply(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?)?;
+
+    account.apply(&Transaction::authorize(1, 2, Decimal::from_str("3.0")?))?;
+    account.apply(&Transaction::authorize(1, 3, Decimal::from_str("4.0")?))?;
+
+    assert_eq!(account.available(), Decimal::from_str("3.0")?);
+    assert_eq!(account.held(), Decimal::from_str("7.0")?);
+    assert_eq!(account.apply(&Transaction::authorize(1, 4, Decimal::from_str("3.5")?)).map_err(|error| error.code()), Err("E_INSUFFICIENT_FUNDS"));
+
+    let captured = account.apply(&Transaction::capture(1, 2))?;
+    account.apply(&Transaction::release(1, 3))?;
+
+    assert_eq!(captured, vec![AccountEvent::Captured { account_id: 1, transaction_id: 2, amount: Decimal::from_str("3.0")?, currency: None }]);
+    assert_eq!(account.available(), Decimal::from_str("7.0")?);
+    assert_eq!(account.held(), Decimal::ZERO);
+    assert_eq!(account.total(), Decimal::from_str("7.0")?);
+
+    // Closed holds cannot be captured or released again, unknown ones are not found, and authorizations cannot be disputed
+    assert_eq!(account.apply(&Transaction::release(1, 2)).map_err(|error| error.code()), Err("E_HOLD_NOT_OPEN"));
+    assert_eq!(account.apply(&Transaction::capture(1, 3)).map_err(|error| error.code()), Err("E_HOLD_NOT_OPEN"));
+    assert_eq!(account.apply(&Transaction::capture(1, 1)).map_err(|error| error.code()), Err("E_TRANSACTION_NOT_FOUND"));
+    assert_eq!(account.apply(&Transaction::dispute(1, 2)).map_err(|error| error.code()), Err("E_TRANSACTION_NOT_FOUND"));
+    assert_eq!(account.apply(&Transaction::authorize(1, 3, Decimal::ONE)).map_err(|error| error.code()), Err("E_DUPLICATE_TRANSACTION"));
+
+    assert_eq!(Account::from_events(1, account.history().iter().map(|entry| entry.event())), account);
+    assert!(account.verify().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_authorization_holds_keep_their_amount_when_the_ledger_entry_is_reused() -> Result<()> {
+    let policy = AccountPolicy::default().with_idempotency(IdempotencyScope::Off);
+    let mut account = Account::new(1);
+
+    account.apply_with(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?, &policy)?;
+    account.apply_with(&Transaction::authorize(1, 2, Decimal::from_str("4.0")?), &policy)?;
+
+    // An open hold's ID is never reused, even without idempotency
+    let reused = account.apply_with(&create_transaction(TransactionType::Deposit, 2, 1, Some("100.0"))?, &policy);
+
+    assert_eq!(reused.map_err(|error| error.code()), Err("E_DUPLICATE_TRANSACTION"));
+
+    account.apply_with(&Transaction::capture(1, 2), &policy)?;
+
+    // Once closed, the replaced ledger entry no longer affects the hold
+    account.apply_with(&create_transaction(TransactionType::Deposit, 2, 1, Some("100.0"))?, &policy)?;
+
+    assert_eq!(account.apply_with(&Transaction::release(1, 2), &policy).map_err(|error| error.code()), Err("E_HOLD_NOT_OPEN"));
+    assert_eq!(account.available(), Decimal::from(106));
+    assert_eq!(account.held(), Decimal::ZERO);
+    assert!(account.verify().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_authorization_holds_expire_under_the_policy() -> Result<()> {
+    let policy = AccountPolicy::default().with_hold_expiry(Duration::from_secs(60));
+    let start = UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut account = Account::new(1);
+
+    account.apply_at(&create_transaction(TransactionType::Deposit, 1, 1, Some("10.0"))?, &policy, start)?;
+    account.apply_at(&Transaction::authorize(1, 3, Decimal::from_str("4.0")?), &policy, start)?;
+    account.apply_at(&Transaction::authorize(1, 2, Decimal::from_str("1.0")?), &policy, start)?;
+    account.apply_at(&Transaction::authorize(1, 4, Decimal::from_str("2.0")?), &AccountPolicy::default(), start)?;
+
+    assert!(account.expire_holds(start + Duration::from_secs(59)).is_empty());
+
+    // A hold past its expiry cannot be captured, even before it was swept
+    let expired_at = start + Duration::from_secs(60);
+
+    assert_eq!(account.apply_at(&Transaction::capture(1, 2), &policy, expired_at).map_err(|error| error.code()), Err("E_HOLD_NOT_OPEN"));
+
+    let expired = account.expire_holds(expired_at);
+
+    assert_eq!(expired, vec![
+        AccountEvent::HoldExpired { account_id: 1, transaction_id: 2, amount: Decimal::from_str("1.0")?, currency: None },
+        AccountEvent::HoldExpired { account_id: 1, transaction_id: 3, amount: Decimal::from_str("4.0")?, currency: None }
+    ]);
+    assert_eq!(account.available(), Decimal::from_str("8.0")?);
+    assert_eq!(account.held(), Decimal::from_str("2.0")?);
+    assert!(account.expire_holds(expired_at + Duration::from_secs(3_600)).is_empty());
+    assert!(account.verify().is_ok());
+
+    Ok(())
+}
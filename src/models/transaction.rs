@@ -1,15 +1,20 @@
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::models::TransactionType;
+use crate::models::{Currency, TransactionType};
 use crate::types::{AccountId, TransactionId};
 
 /// Represents a single row from the input CSV file.
 ///
 /// This struct captures the raw transaction data before it is applied to an account.
-/// The `amount` field is optional because `dispute`, `resolve`, and `chargeback`
-/// types do not carry an amount value in the CSV.
+/// The `amount` field is optional because `dispute`, `resolve`, `chargeback`, `capture`
+/// and `release` types do not carry an amount value in the CSV. Transfers move the amount from the
+/// client to the destination client in the `to` column.
+///
+/// Use [`Transaction::new`] or one of the per-type constructors to build a transaction
+/// outside of this crate, new fields may be added in minor releases.
 #[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
 pub struct Transaction {
     /// The type of operation (deposit, withdrawal, dispute, etc.)
     #[serde(rename = "type")]
@@ -21,5 +26,79 @@ pub struct Transaction {
     #[serde(rename = "client")]
     pub account_id: AccountId,
     /// The amount of funds involved (if applicable).
-    pub amount: Option<Decimal>
+    pub amount: Option<Decimal>,
+    /// The currency of the amount, the default balance of the client when absent. Disputes,
+    /// resolves and chargebacks always act on the currency of the transaction they reference.
+    #[serde(default)]
+    pub currency: Option<Currency>,
+    /// The client credited by a transfer, absent for every other type.
+    #[serde(rename = "to", default)]
+    pub destination_id: Option<AccountId>
+}
+
+impl Transaction {
+    /// Creates a transaction of any type.
+    pub fn new(transaction_type: TransactionType, account_id: AccountId, transaction_id: TransactionId, amount: Option<Decimal>) -> Self {
+        Self {
+            transaction_type,
+            transaction_id,
+            account_id,
+            amount,
+            currency: None,
+            destination_id: None
+        }
+    }
+
+    /// Moves the amount in `currency` instead of the default balance.
+    pub fn with_currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// Creates a deposit crediting `amount` to the client.
+    pub fn deposit(account_id: AccountId, transaction_id: TransactionId, amount: Decimal) -> Self {
+        Self::new(TransactionType::Deposit, account_id, transaction_id, Some(amount))
+    }
+
+    /// Creates a withdrawal debiting `amount` from the client.
+    pub fn withdrawal(account_id: AccountId, transaction_id: TransactionId, amount: Decimal) -> Self {
+        Self::new(TransactionType::Withdrawal, account_id, transaction_id, Some(amount))
+    }
+
+    /// Creates a dispute referencing a previous transaction of the client.
+    pub fn dispute(account_id: AccountId, transaction_id: TransactionId) -> Self {
+        Self::new(TransactionType::Dispute, account_id, transaction_id, None)
+    }
+
+    /// Creates a resolve for an in-progress dispute.
+    pub fn resolve(account_id: AccountId, transaction_id: TransactionId) -> Self {
+        Self::new(TransactionType::Resolve, account_id, transaction_id, None)
+    }
+
+    /// Creates a chargeback for an in-progress dispute.
+    pub fn chargeback(account_id: AccountId, transaction_id: TransactionId) -> Self {
+        Self::new(TransactionType::Chargeback, account_id, transaction_id, None)
+    }
+
+    /// Creates an authorization holding `amount` of the client until it is captured, released or expires.
+    pub fn authorize(account_id: AccountId, transaction_id: TransactionId, amount: Decimal) -> Self {
+        Self::new(TransactionType::Authorize, account_id, transaction_id, Some(amount))
+    }
+
+    /// Creates a capture debiting the amount held by an open authorization.
+    pub fn capture(account_id: AccountId, transaction_id: TransactionId) -> Self {
+        Self::new(TransactionType::Capture, account_id, transaction_id, None)
+    }
+
+    /// Creates a release making the amount held by an open authorization available again.
+    pub fn release(account_id: AccountId, transaction_id: TransactionId) -> Self {
+        Self::new(TransactionType::Release, account_id, transaction_id, None)
+    }
+
+    /// Creates a transfer debiting `amount` from the client and crediting it to `destination_id`.
+    pub fn transfer(account_id: AccountId, destination_id: AccountId, transaction_id: TransactionId, amount: Decimal) -> Self {
+        let mut transaction = Self::new(TransactionType::Transfer, account_id, transaction_id, Some(amount));
+        transaction.destination_id = Some(destination_id);
+        transaction
+    }
 }
@@ -0,0 +1,131 @@
+use std::cmp::Ordering;
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use csv::WriterBuilder;
+use tokio::pin;
+use tokio_stream::StreamExt;
+
+use crate::models::MoneyPolicy;
+use crate::output::{AccountRow, OutputFormat, SortKey};
+use crate::storage::AsyncStorage;
+
+/// Writes the final state of every stored account, sorted, as CSV, JSON or JSON Lines.
+///
+/// Every balance of an account is a row of its own. Rows of a balance in a currency carry a
+/// `currency` field, and the CSV output gains a `currency` column as soon as one of them does,
+/// so inputs without currencies keep the original five columns.
+///
+/// Storage scans come in no particular order, so the rows are collected and sorted before
+/// anything is written and the output is identical between runs over the same state. Only the
+/// five output columns are kept per account, never the ledger or the history.
+#[derive(Debug, Clone, Copy)]
+pub struct AccountsWriter {
+    format: OutputFormat,
+    sort: SortKey,
+    money: MoneyPolicy
+}
+
+impl AccountsWriter {
+    /// Creates a writer sorting by client, with the balances at the scale of the default [`MoneyPolicy`].
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            sort: SortKey::default(),
+            money: MoneyPolicy::default()
+        }
+    }
+
+    /// Rounds the balances to the output scale of `money`, which should be the policy of the engine.
+    pub fn with_money(mut self, money: MoneyPolicy) -> Self {
+        self.money = money;
+        self
+    }
+
+    /// Sorts the accounts by `sort` instead of by client.
+    pub fn with_sort(mut self, sort: SortKey) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Writes every account of the storage into `output`, returning the number of rows written.
+    pub async fn write<S: AsyncStorage>(&self, storage: &S, output: impl Write) -> Result<usize> {
+        let mut rows = Vec::new();
+        let accounts = storage.scan();
+
+        pin!(accounts);
+
+        while let Some(account) = accounts.next().await {
+            rows.extend(AccountRow::rows(&account?, &self.money));
+        }
+
+        self.sort(&mut rows);
+        self.write_rows(&rows, output)?;
+
+        Ok(rows.len())
+    }
+
+    fn sort(&self, rows: &mut [AccountRow]) {
+        let by_key = |first: &AccountRow, second: &AccountRow| match self.sort {
+            SortKey::Client => Ordering::Equal,
+            SortKey::Available => first.available.cmp(&second.available),
+            SortKey::Held => first.held.cmp(&second.held),
+            SortKey::Total => first.total.cmp(&second.total),
+            SortKey::Locked => first.locked.cmp(&second.locked)
+        };
+
+        rows.sort_unstable_by(|first, second| {
+            by_key(first, second)
+                .then_with(|| first.client.cmp(&second.client))
+                .then_with(|| first.currency.cmp(&second.currency))
+        });
+    }
+
+    fn write_rows(&self, rows: &[AccountRow], output: impl Write) -> Result<()> {
+        match self.format {
+            OutputFormat::Csv => {
+                let mut writer = WriterBuilder::new().has_headers(false).from_writer(output);
+                let currencies = rows.iter().any(|row| row.currency.is_some());
+
+                //NOTE: Records are built by hand, serde would drop the currency of default balances and leave their rows short
+                if currencies {
+                    writer.write_record(["client", "currency", "available", "held", "total", "locked"])?;
+                } else {
+                    writer.write_record(["client", "available", "held", "total", "locked"])?;
+                }
+
+                for row in rows {
+                    let mut record = vec![row.client.to_string()];
+
+                    if currencies {
+                        record.push(row.currency.as_ref().map(ToString::to_string).unwrap_or_default());
+                    }
+
+                    record.extend([row.available.to_string(), row.held.to_string(), row.total.to_string(), row.locked.to_string()]);
+                    writer.write_record(&record)?;
+                }
+
+                writer.flush()?;
+            }
+            OutputFormat::Json => {
+                let mut writer = BufWriter::new(output);
+
+                serde_json::to_writer_pretty(&mut writer, rows)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            OutputFormat::JsonLines => {
+                let mut writer = BufWriter::new(output);
+
+                for row in rows {
+                    serde_json::to_writer(&mut writer, row)?;
+                    writeln!(writer)?;
+                }
+
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
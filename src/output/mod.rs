@@ -0,0 +1,118 @@
+mod accounts_writer;
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+
+use crate::models::{Account, Balance, Currency, MoneyPolicy};
+use crate::types::AccountId;
+
+pub use accounts_writer::AccountsWriter;
+
+/// The final state of a single balance of an account, as written to the output, rounded to the output scale.
+///
+/// Accounts holding several currencies yield one row per currency, the default balance has no currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct AccountRow {
+    pub(crate) client: AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) currency: Option<Currency>,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) available: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) held: Decimal,
+    #[serde(serialize_with = "serialize_amount")]
+    pub(crate) total: Decimal,
+    pub(crate) locked: bool
+}
+
+impl AccountRow {
+    pub(crate) fn new(account: &Account, currency: Option<&Currency>, balance: Balance, money: &MoneyPolicy) -> Self {
+        Self {
+            client: account.account_id(),
+            currency: currency.cloned(),
+            available: money.round_output(balance.available()),
+            held: money.round_output(balance.held()),
+            total: money.round_output(balance.total()),
+            locked: account.is_locked()
+        }
+    }
+
+    /// One row per balance of the account.
+    pub(crate) fn rows<'a>(account: &'a Account, money: &'a MoneyPolicy) -> impl Iterator<Item = Self> + 'a {
+        account.balances().map(move |(currency, balance)| Self::new(account, currency, balance, money))
+    }
+}
+
+//NOTE: Amounts are written as strings in every format, JSON included, so no consumer parses them as floats
+fn serialize_amount<S: Serializer>(amount: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(amount)
+}
+
+/// The file format of the account states.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// Comma separated values with a `client,available,held,total,locked` header row.
+    Csv,
+    /// A single JSON array of account objects.
+    Json,
+    /// One JSON account object per line.
+    JsonLines
+}
+
+impl OutputFormat {
+    /// Infers the format from the file extension, `.json` is JSON, `.jsonl` and `.ndjson` are JSON Lines, everything else is CSV.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Self::Json,
+            Some(extension) if extension.eq_ignore_ascii_case("jsonl") || extension.eq_ignore_ascii_case("ndjson") => Self::JsonLines,
+            _ => Self::Csv
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            _ => Err(anyhow!("Invalid output format '{format}', expected 'csv', 'json' or 'jsonl'"))
+        }
+    }
+}
+
+/// The column the account states are sorted by, ascending. Ties are broken by client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SortKey {
+    #[default]
+    Client,
+    Available,
+    Held,
+    Total,
+    Locked
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key.to_lowercase().as_str() {
+            "client" => Ok(Self::Client),
+            "available" => Ok(Self::Available),
+            "held" => Ok(Self::Held),
+            "total" => Ok(Self::Total),
+            "locked" => Ok(Self::Locked),
+            _ => Err(anyhow!("Invalid sort key '{key}', expected 'client', 'available', 'held', 'total' or 'locked'"))
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::{AccountsWriter, OutputFormat, SortKey};
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+
+use crate::models::{Account, Currency, MoneyPolicy, RoundingMode, Transaction};
+use crate::storage::{AccountStorage, Storage};
+
+fn create_storage() -> Result<AccountStorage> {
+    let storage = AccountStorage::new();
+
+    for (client, amount) in [(3, "1.5"), (1, "20"), (2, "1.5")] {
+        let mut account = Account::new(client);
+        account.apply(&Transaction::deposit(client, u32::from(client), Decimal::from_str(amount)?))?;
+        Storage::save(&storage, client, account);
+    }
+
+    Ok(storage)
+}
+
+async fn write(writer: AccountsWriter, storage: &AccountStorage) -> Result<String> {
+    let mut output = Vec::new();
+    writer.write(storage, &mut output).await?;
+
+    Ok(String::from_utf8(output)?)
+}
+
+#[tokio::test]
+async fn test_accounts_writer_emits_csv_sorted_by_client() -> Result<()> {
+    let storage = create_storage()?;
+    let output = write(AccountsWriter::new(OutputFormat::Csv), &storage).await?;
+
+    assert_eq!(output, "client,available,held,total,locked\n1,20.0000,0.0000,20.0000,false\n2,1.5000,0.0000,1.5000,false\n3,1.5000,0.0000,1.5000,false\n");
+    assert_eq!(write(AccountsWriter::new(OutputFormat::Csv), &AccountStorage::new()).await?, "client,available,held,total,locked\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accounts_writer_sorts_by_key_then_client() -> Result<()> {
+    let storage = create_storage()?;
+    let output = write(AccountsWriter::new(OutputFormat::JsonLines).with_sort(SortKey::Total), &storage).await?;
+    let clients: Vec<u64> = output.lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .map(|row| row.map(|row| row["client"].as_u64().unwrap_or_default()))
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(clients, vec![2, 3, 1]);
+    assert_eq!(output.lines().next(), Some(r#"{"client":2,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}"#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accounts_writer_emits_json_array() -> Result<()> {
+    let storage = create_storage()?;
+    let output = write(AccountsWriter::new(OutputFormat::Json), &storage).await?;
+    let rows: Vec<serde_json::Value> = serde_json::from_str(&output)?;
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["client"], 1);
+    assert_eq!(rows[0]["total"], "20.0000");
+    assert_eq!(write(AccountsWriter::new(OutputFormat::Json), &AccountStorage::new()).await?, "[]\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accounts_writer_applies_output_scale() -> Result<()> {
+    let storage = create_storage()?;
+    let money = MoneyPolicy::default().with_rounding(RoundingMode::Bankers).with_output_scale(0);
+    let output = write(AccountsWriter::new(OutputFormat::Csv).with_money(money), &storage).await?;
+
+    assert_eq!(output, "client,available,held,total,locked\n1,20,0,20,false\n2,2,0,2,false\n3,2,0,2,false\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accounts_writer_emits_one_row_per_currency() -> Result<()> {
+    let storage = create_storage()?;
+    let usd = Currency::from_str("USD")?;
+
+    let mut account = Account::new(4);
+    account.apply(&Transaction::deposit(4, 4, Decimal::from_str("2.5")?).with_currency(usd.clone()))?;
+    Storage::save(&storage, 4, account);
+
+    let mut account = Storage::load(&storage, 2).ok_or_else(|| anyhow::anyhow!("Account missing"))?;
+    account.apply(&Transaction::deposit(2, 5, Decimal::ONE).with_currency(usd))?;
+    Storage::save(&storage, 2, account);
+
+    let output = write(AccountsWriter::new(OutputFormat::Csv), &storage).await?;
+
+    assert_eq!(output, concat!(
+        "client,currency,available,held,total,locked\n",
+        "1,,20.0000,0.0000,20.0000,false\n",
+        "2,,1.5000,0.0000,1.5000,false\n",
+        "2,USD,1.0000,0.0000,1.0000,false\n",
+        "3,,1.5000,0.0000,1.5000,false\n",
+        "4,USD,2.5000,0.0000,2.5000,false\n"
+    ));
+
+    let output = write(AccountsWriter::new(OutputFormat::JsonLines), &storage).await?;
+
+    assert_eq!(output.lines().nth(2), Some(r#"{"client":2,"currency":"USD","available":"1.0000","held":"0.0000","total":"1.0000","locked":false}"#));
+    assert_eq!(output.lines().next(), Some(r#"{"client":1,"available":"20.0000","held":"0.0000","total":"20.0000","locked":false}"#));
+
+    Ok(())
+}
+
+#[test]
+fn test_output_format_and_sort_key_parsing() -> Result<()> {
+    assert_eq!(OutputFormat::from_path(Path::new("accounts.JSON")), OutputFormat::Json);
+    assert_eq!(OutputFormat::from_path(Path::new("accounts.ndjson")), OutputFormat::JsonLines);
+    assert_eq!(OutputFormat::from_path(Path::new("accounts")), OutputFormat::Csv);
+    assert_eq!(OutputFormat::from_str("jsonl")?, OutputFormat::JsonLines);
+    assert!(OutputFormat::from_str("xml").is_err());
+    assert_eq!(SortKey::from_str("Held")?, SortKey::Held);
+    assert!(SortKey::from_str("amount").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,108 @@
+mod rejects_channel;
+mod rejects_writer;
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+
+use crate::actors::Origin;
+use crate::models::{AccountError, Transaction, TransactionType};
+use crate::types::{AccountId, TransactionId};
+
+pub(crate) use rejects_channel::{RejectsChannel, RejectsSender};
+pub use rejects_writer::RejectsWriter;
+
+/// Reason code used for rows that could not be parsed into a transaction.
+pub const MALFORMED_ROW: &str = "E_MALFORMED_ROW";
+
+/// A single rejected row or transaction, as written to the rejects report.
+///
+/// Fields that could not be determined (e.g. the client of an unparsable row, or the line
+/// of a transaction that was streamed rather than read from an input) are left empty.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct Rejection {
+    /// Display name of the input the row was read from.
+    pub input: Option<String>,
+    /// The 1-based line number of the row inside its input.
+    pub line: Option<u64>,
+    /// The client the row was addressed to.
+    #[serde(rename = "client")]
+    pub account_id: Option<AccountId>,
+    /// The transaction ID of the row.
+    #[serde(rename = "tx")]
+    pub transaction_id: Option<TransactionId>,
+    /// The type of the transaction, if the row could be parsed.
+    #[serde(rename = "type")]
+    pub transaction_type: Option<TransactionType>,
+    /// Stable, machine-readable reason code (e.g. `E_INSUFFICIENT_FUNDS`).
+    pub reason: &'static str,
+    /// Human readable description of the rejection.
+    pub message: String,
+    /// The original row as read from the input.
+    pub row: Option<String>
+}
+
+impl Rejection {
+    pub(crate) fn account(transaction: &Transaction, origin: Option<&Origin>, error: &AccountError) -> Self {
+        Self {
+            input: origin.map(|origin| origin.input.to_string()),
+            line: origin.map(|origin| origin.line),
+            account_id: Some(transaction.account_id),
+            transaction_id: Some(transaction.transaction_id),
+            transaction_type: Some(transaction.transaction_type),
+            reason: error.code(),
+            message: error.to_string(),
+            row: origin.map(|origin| origin.row.clone())
+        }
+    }
+
+    pub(crate) fn malformed(origin: &Origin, account_id: Option<AccountId>, transaction_id: Option<TransactionId>, message: String) -> Self {
+        Self {
+            input: Some(origin.input.to_string()),
+            line: Some(origin.line),
+            account_id,
+            transaction_id,
+            transaction_type: None,
+            reason: MALFORMED_ROW,
+            message,
+            row: Some(origin.row.clone())
+        }
+    }
+}
+
+/// The file format of the rejects report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectsFormat {
+    /// Comma separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    JsonLines
+}
+
+impl RejectsFormat {
+    /// Infers the format from the file extension, `.jsonl` and `.ndjson` are JSON Lines, everything else is CSV.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("jsonl") || extension.eq_ignore_ascii_case("ndjson") => Self::JsonLines,
+            _ => Self::Csv
+        }
+    }
+}
+
+impl FromStr for RejectsFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            _ => Err(anyhow!("Invalid rejects format '{format}', expected 'csv' or 'jsonl'"))
+        }
+    }
+}
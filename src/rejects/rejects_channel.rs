@@ -0,0 +1,99 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{spawn_blocking, JoinHandle};
+use tracing::error;
+
+use crate::rejects::{Rejection, RejectsWriter};
+
+enum Message {
+    Rejection(Rejection),
+    /// Answered once every rejection sent before is durably written.
+    Sync(oneshot::Sender<Result<()>>)
+}
+
+/// Hands rejections from the reader and the actors to the writer of a [`RejectsChannel`].
+#[derive(Clone)]
+pub struct RejectsSender {
+    sender: UnboundedSender<Message>
+}
+
+impl RejectsSender {
+    pub fn send(&self, rejection: Rejection) {
+        let _ = self.sender.send(Message::Rejection(rejection));
+    }
+}
+
+/// Per-run channel funnelling rejections from the reader and the actors into the rejects writer.
+pub struct RejectsChannel {
+    sender: Option<UnboundedSender<Message>>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl RejectsChannel {
+    /// Spawns the writer task if a rejects report is configured.
+    pub fn open(writer: Option<&Arc<Mutex<RejectsWriter>>>) -> Self {
+        let Some(writer) = writer.cloned() else {
+            return Self { sender: None, handle: None }
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Message>();
+
+        let handle = spawn_blocking(move || {
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+            while let Some(message) = receiver.blocking_recv() {
+                match message {
+                    Message::Rejection(rejection) => if let Err(error) = writer.write(&rejection) {
+                        error!("Rejects report write failed: {error}");
+                    },
+                    Message::Sync(synced) => {
+                        let _ = synced.send(writer.sync());
+                    }
+                }
+            }
+
+            if let Err(error) = writer.flush() {
+                error!("Rejects report flush failed: {error}");
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            handle: Some(handle)
+        }
+    }
+
+    pub fn sender(&self) -> Option<RejectsSender> {
+        self.sender.clone().map(|sender| RejectsSender { sender })
+    }
+
+    /// Waits until every rejection sent so far is written and synced to the disk.
+    ///
+    /// The channel is ordered, so a rejection sent before the call (e.g. by an actor that has since
+    /// been passivated) is always written before the sync is answered.
+    pub async fn sync(&self) -> Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(())
+        };
+
+        let (synced, receiver) = oneshot::channel();
+
+        sender.send(Message::Sync(synced)).map_err(|_| anyhow!("Rejects report writer has stopped"))?;
+        receiver.await.map_err(|_| anyhow!("Rejects report writer has stopped"))?
+    }
+
+    /// Waits for every outstanding rejection to be written.
+    ///
+    /// All senders handed out by [`RejectsChannel::sender`] must have been dropped beforehand.
+    pub async fn close(mut self) {
+        drop(self.sender.take());
+
+        if let Some(handle) = self.handle.take()
+            && let Err(error) = handle.await {
+            error!("Rejects report writer failed: {error}");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::ffi::OsString;
+use std::fs::{rename, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use csv::{ReaderBuilder, WriterBuilder};
+
+use crate::checkpoint::Checkpoint;
+use crate::rejects::{Rejection, RejectsFormat};
+use crate::storage::terminate_partial_line;
+
+enum Sink {
+    Csv(Box<csv::Writer<Box<dyn Write + Send>>>),
+    JsonLines(BufWriter<Box<dyn Write + Send>>)
+}
+
+/// Writes every rejection of the engine to a CSV or JSON Lines report.
+///
+/// The writer is kept by the engine across runs, so repeated runs append to the same report.
+pub struct RejectsWriter {
+    sink: Sink,
+    file: Option<File>
+}
+
+impl RejectsWriter {
+    /// Creates a writer reporting into any output.
+    pub fn new(output: impl Write + Send + 'static, format: RejectsFormat) -> Self {
+        Self::with_header(output, format, true)
+    }
+
+    fn with_header(output: impl Write + Send + 'static, format: RejectsFormat, header: bool) -> Self {
+        let output: Box<dyn Write + Send> = Box::new(output);
+
+        let sink = match format {
+            RejectsFormat::Csv => Sink::Csv(Box::new(WriterBuilder::new().has_headers(header).from_writer(output))),
+            RejectsFormat::JsonLines => Sink::JsonLines(BufWriter::new(output))
+        };
+
+        Self { sink, file: None }
+    }
+
+    /// Creates (or truncates) the report file at `path`.
+    pub fn create(path: impl AsRef<Path>, format: RejectsFormat) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Error creating rejects report at path: {}", path.display()))?;
+
+        Ok(Self::new(file.try_clone()?, format).with_file(file))
+    }
+
+    /// Continues the report file at `path`, creating it when missing.
+    ///
+    /// The CSV header is only written into a new or empty file, and a partially written last line is ended first.
+    pub fn append(path: impl AsRef<Path>, format: RejectsFormat) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Error opening rejects report at path: {}", path.display()))?;
+
+        let empty = file.metadata()?.len() == 0;
+        terminate_partial_line(&mut file)?;
+
+        Ok(Self::with_header(file.try_clone()?, format, empty).with_file(file))
+    }
+
+    /// Continues the report file at `path` for a run resumed from `checkpoint`, creating it when missing.
+    ///
+    /// Every rejection of the rows before the checkpoint was synced when the checkpoint was recorded.
+    /// The entries of the rows after it (and a partially written last entry) are dropped, since the
+    /// resumed run reads and reports those rows again.
+    pub fn resume(path: impl AsRef<Path>, format: RejectsFormat, checkpoint: &Checkpoint) -> Result<Self> {
+        let path = path.as_ref();
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Self::create(path, format),
+            Err(error) => return Err(error).with_context(|| format!("Error opening rejects report at path: {}", path.display()))
+        };
+
+        let position = checkpoint.position();
+        let before_checkpoint = |input: Option<&str>, line: Option<u64>| {
+            match (input.and_then(|input| checkpoint.inputs().iter().position(|name| name == input)), line) {
+                (Some(input), Some(line)) => (input, line) < (position.input, position.line),
+                _ => true
+            }
+        };
+
+        let temporary_path = temporary_path(path);
+        let mut output = BufWriter::new(File::create(&temporary_path)?);
+
+        match format {
+            RejectsFormat::Csv => {
+                let mut reader = ReaderBuilder::new().flexible(true).from_reader(file);
+                let headers = reader.headers()?.clone();
+                let mut writer = WriterBuilder::new().from_writer(&mut output);
+                let input = headers.iter().position(|header| header == "input");
+                let line = headers.iter().position(|header| header == "line");
+
+                if !headers.is_empty() {
+                    writer.write_record(&headers)?;
+                }
+
+                for record in reader.records() {
+                    let Ok(record) = record else {
+                        continue
+                    };
+
+                    let field = |index: Option<usize>| index.and_then(|index| record.get(index));
+
+                    if record.len() == headers.len() && before_checkpoint(field(input), field(line).and_then(|line| line.parse().ok())) {
+                        writer.write_record(&record)?;
+                    }
+                }
+
+                writer.flush()?;
+            }
+            RejectsFormat::JsonLines => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+
+                    let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+                        continue
+                    };
+
+                    if before_checkpoint(entry["input"].as_str(), entry["line"].as_u64()) {
+                        writeln!(output, "{line}")?;
+                    }
+                }
+            }
+        }
+
+        let output = output.into_inner().map_err(|error| error.into_error())?;
+        output.sync_all()?;
+        rename(&temporary_path, path)?;
+
+        Self::append(path, format)
+    }
+
+    fn with_file(mut self, file: File) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    pub(crate) fn write(&mut self, rejection: &Rejection) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.serialize(rejection)?,
+            Sink::JsonLines(writer) => {
+                serde_json::to_writer(&mut *writer, rejection)?;
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.flush()?,
+            Sink::JsonLines(writer) => writer.flush()?
+        }
+
+        Ok(())
+    }
+
+    /// Flushes every written rejection and, for report files, syncs it to the disk.
+    pub(crate) fn sync(&mut self) -> Result<()> {
+        self.flush()?;
+
+        if let Some(file) = &self.file {
+            file.sync_data()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,162 @@
+use super::{Rejection, RejectsFormat, RejectsWriter, MALFORMED_ROW};
+
+use std::fs::{read_to_string, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use tempfile::NamedTempFile;
+
+use crate::actors::Origin;
+use crate::checkpoint::{Checkpoint, InputPosition};
+use crate::models::{AccountError, Transaction};
+
+/// An in-memory output that can still be inspected after being moved into a writer.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn create_origin(line: u64, row: &str) -> Origin {
+    Origin {
+        input: Arc::from("input.csv"),
+        line,
+        row: row.to_string()
+    }
+}
+
+#[test]
+fn test_rejects_writer_emits_csv_with_header() -> Result<()> {
+    let buffer = SharedBuffer::default();
+    let mut writer = RejectsWriter::new(buffer.clone(), RejectsFormat::Csv);
+
+    let transaction = Transaction::withdrawal(1, 2, Decimal::TEN);
+    let error = AccountError::insufficient_funds(&transaction);
+
+    writer.write(&Rejection::account(&transaction, Some(&create_origin(3, "withdrawal,1,2,10")), &error))?;
+    writer.write(&Rejection::malformed(&create_origin(4, "invalid,data,9,0"), None, Some(9), "bad row".to_string()))?;
+    writer.flush()?;
+
+    let contents = buffer.contents();
+    let mut lines = contents.lines();
+
+    assert_eq!(lines.next(), Some("input,line,client,tx,type,reason,message,row"));
+    assert_eq!(lines.next(), Some("input.csv,3,1,2,withdrawal,E_INSUFFICIENT_FUNDS,Insufficient funds for transaction [2]:[Withdrawal] for client [1],\"withdrawal,1,2,10\""));
+    assert_eq!(lines.next(), Some("input.csv,4,,9,,E_MALFORMED_ROW,bad row,\"invalid,data,9,0\""));
+
+    Ok(())
+}
+
+#[test]
+fn test_rejects_writer_emits_one_json_object_per_line() -> Result<()> {
+    let buffer = SharedBuffer::default();
+    let mut writer = RejectsWriter::new(buffer.clone(), RejectsFormat::JsonLines);
+
+    let transaction = Transaction::dispute(5, 42);
+    let error = AccountError::transaction_not_found(&transaction);
+
+    writer.write(&Rejection::account(&transaction, None, &error))?;
+    writer.flush()?;
+
+    let contents = buffer.contents();
+    let value: serde_json::Value = serde_json::from_str(contents.trim_end())?;
+
+    assert_eq!(contents.lines().count(), 1);
+    assert_eq!(value["client"], 5);
+    assert_eq!(value["tx"], 42);
+    assert_eq!(value["type"], "dispute");
+    assert_eq!(value["reason"], "E_TRANSACTION_NOT_FOUND");
+    assert!(value["line"].is_null());
+
+    Ok(())
+}
+
+#[test]
+fn test_rejects_writer_appends_to_an_existing_report() -> Result<()> {
+    let file = NamedTempFile::new()?;
+    let transaction = Transaction::withdrawal(1, 2, Decimal::TEN);
+    let error = AccountError::insufficient_funds(&transaction);
+
+    for line in [3, 8] {
+        let mut writer = RejectsWriter::append(file.path(), RejectsFormat::Csv)?;
+        writer.write(&Rejection::account(&transaction, Some(&create_origin(line, "withdrawal,1,2,10")), &error))?;
+        writer.flush()?;
+    }
+
+    let contents = read_to_string(file.path())?;
+    let lines: Vec<&str> = contents.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "input,line,client,tx,type,reason,message,row");
+    assert!(lines[1].starts_with("input.csv,3,"));
+    assert!(lines[2].starts_with("input.csv,8,"));
+
+    Ok(())
+}
+
+#[test]
+fn test_rejects_writer_resumes_a_report_at_a_checkpoint() -> Result<()> {
+    let transaction = Transaction::withdrawal(1, 2, Decimal::TEN);
+    let error = AccountError::insufficient_funds(&transaction);
+    let position = InputPosition { input: 0, byte: 0, line: 5, record: 4 };
+    let checkpoint = Checkpoint::new(vec!["input.csv".to_string()], position, Vec::new());
+
+    for format in [RejectsFormat::Csv, RejectsFormat::JsonLines] {
+        let file = NamedTempFile::new()?;
+
+        //NOTE: Line 7 was reported after the checkpoint, before the interrupted run was killed part way through line 9
+        let mut writer = RejectsWriter::create(file.path(), format)?;
+
+        for line in [3, 7, 4] {
+            writer.write(&Rejection::account(&transaction, Some(&create_origin(line, "withdrawal,1,2,10")), &error))?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+
+        let mut report = OpenOptions::new().append(true).open(file.path())?;
+        write!(report, "input.csv,9,1,2,withdr")?;
+        drop(report);
+
+        let mut writer = RejectsWriter::resume(file.path(), format, &checkpoint)?;
+        writer.write(&Rejection::account(&transaction, Some(&create_origin(7, "withdrawal,1,2,10")), &error))?;
+        writer.flush()?;
+
+        let contents = read_to_string(file.path())?;
+        let lines: Vec<&str> = contents.lines().filter(|line| !line.starts_with("input,")).collect();
+
+        assert_eq!(lines.len(), 3, "{format:?}");
+        assert!(lines[0].contains("\"line\":3") || lines[0].starts_with("input.csv,3,"));
+        assert!(lines[1].contains("\"line\":4") || lines[1].starts_with("input.csv,4,"));
+        assert!(lines[2].contains("\"line\":7") || lines[2].starts_with("input.csv,7,"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_rejects_format_is_inferred_and_parsed() -> Result<()> {
+    assert_eq!(RejectsFormat::from_path(Path::new("rejects.jsonl")), RejectsFormat::JsonLines);
+    assert_eq!(RejectsFormat::from_path(Path::new("rejects.csv")), RejectsFormat::Csv);
+    assert_eq!("JSONL".parse::<RejectsFormat>()?, RejectsFormat::JsonLines);
+    assert!("xml".parse::<RejectsFormat>().is_err());
+    assert_eq!(MALFORMED_ROW, "E_MALFORMED_ROW");
+
+    Ok(())
+}
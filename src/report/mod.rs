@@ -0,0 +1,5 @@
+mod run_report;
+#[cfg(test)]
+mod tests;
+
+pub use run_report::{PhaseTimings, RunReport};
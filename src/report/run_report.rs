@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+use crate::models::{AccountError, TransactionType};
+
+/// Summary of a single engine run, returned by every `AsyncEngine::run*` method.
+#[derive(Debug, Clone, Default, Serialize)]
+#[non_exhaustive]
+pub struct RunReport {
+    /// Rows (or streamed transactions) received, including the ones that failed to parse.
+    pub rows_read: u64,
+    /// Rows that could not be parsed into a transaction.
+    pub parse_failures: u64,
+    /// Transactions successfully applied, per transaction type.
+    pub applied: BTreeMap<TransactionType, u64>,
+    /// Transactions rejected by an account, per transaction type.
+    pub rejected: BTreeMap<TransactionType, u64>,
+    /// Transactions rejected by an account, per `AccountError` code.
+    pub rejected_by_reason: BTreeMap<&'static str, u64>,
+    /// Distinct clients that received at least one transaction.
+    pub accounts_touched: u64,
+    /// Accounts that became locked during the run.
+    pub accounts_locked: u64,
+    /// Authorization holds released automatically because they expired.
+    pub holds_expired: u64,
+    /// Actors spawned (or re-hydrated) during the run.
+    pub actors_spawned: u64,
+    /// Actors passivated before the end of the input because they were idle or the cache was full.
+    pub actors_passivated: u64,
+    /// Checkpoints recorded during the run.
+    pub checkpoints_written: u64,
+    /// Wall-clock time spent in each phase of the run.
+    pub timings: PhaseTimings
+}
+
+/// Wall-clock timings of the phases of a run, serialized in milliseconds.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[non_exhaustive]
+pub struct PhaseTimings {
+    /// Reading and dispatching transactions until the input is exhausted.
+    #[serde(rename = "ingestion_ms", serialize_with = "serialize_millis")]
+    pub ingestion: Duration,
+    /// Draining the actors and persisting their state once the input is exhausted.
+    #[serde(rename = "passivation_ms", serialize_with = "serialize_millis")]
+    pub passivation: Duration,
+    /// The whole run.
+    #[serde(rename = "total_ms", serialize_with = "serialize_millis")]
+    pub total: Duration
+}
+
+impl RunReport {
+    /// Total number of transactions applied.
+    pub fn applied_total(&self) -> u64 {
+        self.applied.values().sum()
+    }
+
+    /// Total number of transactions rejected by an account.
+    pub fn rejected_total(&self) -> u64 {
+        self.rejected.values().sum()
+    }
+
+    pub(crate) fn record_applied(&mut self, transaction_type: TransactionType) {
+        *self.applied.entry(transaction_type).or_default() += 1;
+    }
+
+    pub(crate) fn record_rejected(&mut self, transaction_type: TransactionType, error: &AccountError) {
+        *self.rejected.entry(transaction_type).or_default() += 1;
+        *self.rejected_by_reason.entry(error.code()).or_default() += 1;
+    }
+
+    /// Folds the tallies of a single actor into the run totals.
+    pub(crate) fn merge(&mut self, other: RunReport) {
+        for (transaction_type, count) in other.applied {
+            *self.applied.entry(transaction_type).or_default() += count;
+        }
+
+        for (transaction_type, count) in other.rejected {
+            *self.rejected.entry(transaction_type).or_default() += count;
+        }
+
+        for (reason, count) in other.rejected_by_reason {
+            *self.rejected_by_reason.entry(reason).or_default() += count;
+        }
+
+        self.accounts_locked += other.accounts_locked;
+        self.holds_expired += other.holds_expired;
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
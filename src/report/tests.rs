@@ -0,0 +1,46 @@
+use super::RunReport;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+
+use crate::models::{AccountError, Transaction, TransactionType};
+
+#[test]
+fn test_report_merges_actor_tallies() {
+    let withdrawal = Transaction::withdrawal(1, 1, Decimal::TEN);
+
+    let mut first = RunReport::default();
+    first.record_applied(TransactionType::Deposit);
+    first.record_rejected(TransactionType::Withdrawal, &AccountError::insufficient_funds(&withdrawal));
+
+    let mut second = RunReport::default();
+    second.record_applied(TransactionType::Deposit);
+    second.record_applied(TransactionType::Chargeback);
+    second.accounts_locked = 1;
+
+    let mut total = RunReport::default();
+    total.merge(first);
+    total.merge(second);
+
+    assert_eq!(total.applied[&TransactionType::Deposit], 2);
+    assert_eq!(total.applied_total(), 3);
+    assert_eq!(total.rejected_total(), 1);
+    assert_eq!(total.rejected_by_reason["E_INSUFFICIENT_FUNDS"], 1);
+    assert_eq!(total.accounts_locked, 1);
+}
+
+#[test]
+fn test_report_serializes_counts_and_timings_in_milliseconds() -> Result<()> {
+    let mut report = RunReport::default();
+    report.record_applied(TransactionType::Deposit);
+    report.timings.total = Duration::from_millis(1500);
+
+    let value = serde_json::to_value(&report)?;
+
+    assert_eq!(value["applied"]["deposit"], 1);
+    assert_eq!(value["timings"]["total_ms"], 1500.0);
+
+    Ok(())
+}
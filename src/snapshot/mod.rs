@@ -0,0 +1,139 @@
+mod snapshot_file;
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::models::Account;
+use crate::storage::{AccountStorage, AsyncStorage, Storage};
+
+pub(crate) use snapshot_file::{read_accounts_file, write_accounts_file};
+
+const FORMAT: &str = "async-transaction-engine/snapshot";
+/// 2: Accounts carry their hash chained history, balances per currency and authorization holds.
+const VERSION: u32 = 2;
+/// The oldest version still read, its accounts are migrated on load.
+const OLDEST_VERSION: u32 = 1;
+
+/// A point-in-time copy of every account, including its ledger and disputes.
+///
+/// Snapshots are stored as JSON Lines: a versioned header, one account per line sorted by client,
+/// and a SHA-256 checksum of everything before it. A truncated or altered file is refused on load.
+/// They are meant to hand the end-of-day state of one run over to the next.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    taken_at: u64,
+    accounts: Vec<Account>
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    taken_at: u64,
+    accounts: usize
+}
+
+impl Snapshot {
+    /// Creates a snapshot of the given accounts, taken now.
+    pub fn from_accounts(mut accounts: Vec<Account>) -> Self {
+        accounts.sort_by_key(|account| account.account_id);
+
+        Self {
+            taken_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()),
+            accounts
+        }
+    }
+
+    /// Copies every account out of `storage`.
+    ///
+    /// The storage should be at rest (no run in progress), otherwise accounts held by live actors are missed.
+    pub async fn capture<S: AsyncStorage>(storage: &S) -> Result<Self> {
+        let accounts: Vec<Account> = storage.scan().collect::<Result<_, _>>().await?;
+
+        Ok(Self::from_accounts(accounts))
+    }
+
+    /// Reads and verifies a snapshot file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, has an unsupported version, or fails its checksum.
+    /// Snapshots of older supported versions are migrated to the current one.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (header, mut accounts) = read_accounts_file::<Header>(path, |header| {
+            if header.format != FORMAT {
+                return Err(anyhow!("Not a snapshot: {}", path.display()))
+            }
+
+            if !(OLDEST_VERSION..=VERSION).contains(&header.version) {
+                return Err(anyhow!("Unsupported snapshot version [{}] in: {}", header.version, path.display()))
+            }
+
+            Ok(())
+        })?;
+
+        if accounts.len() != header.accounts {
+            return Err(anyhow!("Snapshot holds [{}] of [{}] accounts: {}", accounts.len(), header.accounts, path.display()))
+        }
+
+        //NOTE: Version 1 snapshots may hold accounts written before histories existed
+        if header.version < VERSION {
+            for account in &mut accounts {
+                account.migrate_history();
+            }
+        }
+
+        Ok(Self {
+            taken_at: header.taken_at,
+            accounts
+        })
+    }
+
+    /// Writes the snapshot to `path`, atomically replacing any previous file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let header = Header {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            taken_at: self.taken_at,
+            accounts: self.accounts.len()
+        };
+
+        write_accounts_file(path.as_ref(), &header, &self.accounts)
+    }
+
+    /// When the snapshot was taken, in seconds since the Unix epoch.
+    pub fn taken_at(&self) -> u64 {
+        self.taken_at
+    }
+
+    /// Every account of the snapshot, sorted by client.
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// Saves every account of the snapshot into `storage`, replacing their current state.
+    pub async fn restore<S: AsyncStorage>(&self, storage: &S) -> Result<()> {
+        for account in &self.accounts {
+            storage.save(account.account_id, account.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Seeds a fresh in-memory storage with every account of the snapshot.
+    pub fn into_storage(self) -> AccountStorage {
+        let storage = AccountStorage::new();
+
+        for account in self.accounts {
+            Storage::save(&storage, account.account_id, account);
+        }
+
+        storage
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs::{rename, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::Account;
+
+const CHECKSUM_PREFIX: &str = "sha256:";
+
+#[derive(Serialize, Deserialize)]
+struct Trailer {
+    checksum: String
+}
+
+/// Hashes everything written through it, so the checksum covers exactly the bytes on disk.
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buffer)?;
+        self.hasher.update(&buffer[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes a header line, one account per line and a SHA-256 trailer covering every line before it.
+///
+/// The file is written to a temporary path, synced and atomically renamed into place.
+pub(crate) fn write_accounts_file<H: Serialize>(path: &Path, header: &H, accounts: &[Account]) -> Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+
+    let file = File::create(&temporary_path)
+        .with_context(|| format!("Error creating: {}", Path::new(&temporary_path).display()))?;
+
+    let mut writer = HashingWriter {
+        inner: BufWriter::new(file),
+        hasher: Sha256::new()
+    };
+
+    serde_json::to_writer(&mut writer, header)?;
+    writer.write_all(b"\n")?;
+
+    for account in accounts {
+        serde_json::to_writer(&mut writer, account)?;
+        writer.write_all(b"\n")?;
+    }
+
+    let HashingWriter { mut inner, hasher } = writer;
+    let trailer = Trailer {
+        checksum: format!("{CHECKSUM_PREFIX}{}", to_hex(&hasher.finalize()))
+    };
+
+    serde_json::to_writer(&mut inner, &trailer)?;
+    inner.write_all(b"\n")?;
+
+    let file = inner.into_inner().map_err(|error| error.into_error())?;
+    file.sync_all()?;
+    rename(&temporary_path, path)
+        .with_context(|| format!("Error writing: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Reads a file written by [`write_accounts_file`], verifying its checksum.
+///
+/// `validate` inspects the header before any account is parsed, so unsupported versions are refused early.
+pub(crate) fn read_accounts_file<H: DeserializeOwned>(path: &Path, validate: impl FnOnce(&H) -> Result<()>) -> Result<(H, Vec<Account>)> {
+    let file = File::open(path)
+        .with_context(|| format!("Error opening: {}", path.display()))?;
+
+    let mut lines = BufReader::new(file).lines();
+    let mut hasher = Sha256::new();
+
+    let first = lines.next().ok_or_else(|| anyhow!("File is empty: {}", path.display()))??;
+    hasher.update(first.as_bytes());
+    hasher.update(b"\n");
+
+    let header: H = serde_json::from_str(&first)
+        .with_context(|| format!("Unreadable header in: {}", path.display()))?;
+
+    validate(&header)?;
+
+    let mut accounts = Vec::new();
+    let mut pending: Option<String> = None;
+
+    //NOTE: The last line is the trailer, so every line is only parsed as an account once the next one is read
+    for line in lines {
+        if let Some(previous) = pending.replace(line?) {
+            hasher.update(previous.as_bytes());
+            hasher.update(b"\n");
+            accounts.push(serde_json::from_str::<Account>(&previous)
+                .with_context(|| format!("Unreadable account in: {}", path.display()))?);
+        }
+    }
+
+    let trailer: Trailer = pending
+        .and_then(|line| serde_json::from_str(&line).ok())
+        .ok_or_else(|| anyhow!("Missing checksum, the file is incomplete: {}", path.display()))?;
+
+    if trailer.checksum != format!("{CHECKSUM_PREFIX}{}", to_hex(&hasher.finalize())) {
+        return Err(anyhow!("Checksum mismatch, the file is corrupted: {}", path.display()))
+    }
+
+    Ok((header, accounts))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,96 @@
+use super::{write_accounts_file, Header, Snapshot, FORMAT, VERSION};
+
+use std::fs::{read_to_string, write};
+
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use tempfile::tempdir;
+
+use crate::models::{Account, Transaction};
+use crate::storage::{AccountStorage, Storage};
+
+#[tokio::test]
+async fn test_snapshot_round_trips_every_account_into_a_fresh_storage() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("end-of-day.snapshot");
+    let storage = AccountStorage::new();
+
+    let mut disputed = Account::new(2);
+    disputed.apply(&Transaction::deposit(2, 1, Decimal::TEN))?;
+    disputed.apply(&Transaction::dispute(2, 1))?;
+    storage.save(2, disputed);
+    storage.save(1, Account::new(1));
+
+    Snapshot::capture(&storage).await?.write(&path)?;
+
+    let snapshot = Snapshot::load(&path)?;
+
+    assert_eq!(snapshot.accounts().iter().map(|account| account.account_id).collect::<Vec<_>>(), vec![1, 2]);
+    assert!(snapshot.taken_at() > 0);
+
+    let restored = snapshot.into_storage();
+    let mut account = restored.load(2).ok_or_else(|| anyhow!("Account missing from restored storage"))?;
+
+    assert_eq!(account.held(), Decimal::TEN);
+
+    // The private ledger and disputes survive the hand-off, so the dispute can be charged back the next day
+    account.apply(&Transaction::chargeback(2, 1))?;
+
+    assert!(account.is_locked());
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_refuses_corrupted_and_truncated_files() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("end-of-day.snapshot");
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::TEN))?;
+    Snapshot::from_accounts(vec![account, Account::new(2)]).write(&path)?;
+
+    let contents = read_to_string(&path)?;
+
+    write(&path, contents.replace("\"10\"", "\"99\""))?;
+    let corrupted = Snapshot::load(&path).unwrap_err();
+
+    assert!(corrupted.to_string().contains("Checksum mismatch"));
+
+    let truncated: Vec<&str> = contents.lines().take(2).collect();
+    write(&path, truncated.join("\n"))?;
+
+    assert!(Snapshot::load(&path).is_err());
+
+    write(&path, &contents)?;
+
+    assert_eq!(Snapshot::load(&path)?.accounts().len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_migrates_older_versions_and_refuses_newer_ones() -> Result<()> {
+    let directory = tempdir()?;
+    let path = directory.path().join("end-of-day.snapshot");
+
+    // Version 1 snapshots may hold accounts written before histories existed
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::TEN))?;
+    account.history.clear();
+
+    let header = |version| Header { format: FORMAT.to_string(), version, taken_at: 1, accounts: 1 };
+
+    write_accounts_file(&path, &header(1), &[account])?;
+    let snapshot = Snapshot::load(&path)?;
+
+    assert_eq!(snapshot.accounts()[0].history().len(), 1);
+    assert!(snapshot.accounts()[0].verify().is_ok());
+
+    write_accounts_file(&path, &header(VERSION + 1), &[Account::new(1)])?;
+    let newer = Snapshot::load(&path).unwrap_err();
+
+    assert!(newer.to_string().contains("Unsupported snapshot version"));
+
+    Ok(())
+}
@@ -4,25 +4,37 @@ use dashmap::iter::Iter;
 use dashmap::DashMap;
 
 use crate::models::Account;
-use crate::storage::Storage;
+use crate::storage::{ReadableStorage, Storage};
 use crate::types::AccountId;
 
+/// In-memory storage backed by a concurrent `DashMap`.
+///
+/// Loading an account removes it from the map, ownership of the state moves to the actor
+/// until it is saved again on passivation. Use `get` or `scan` to read without taking ownership.
 pub struct AccountStorage {
     cache: Arc<DashMap<AccountId, Account>>
 }
 
 impl AccountStorage {
+    /// Creates an empty storage.
     pub fn new() -> Self {
         Self {
             cache: Arc::new(DashMap::new())
         }
     }
 
+    /// Iterates over all passivated accounts.
     pub fn iter(&self) -> Iter<'_, AccountId, Account> {
         self.cache.iter()
     }
 }
 
+impl Default for AccountStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Storage for AccountStorage {
     fn load(&self, account_id: AccountId) -> Option<Account> {
         self.cache.remove(&account_id).map(|(_, account)| account)
@@ -32,3 +44,20 @@ impl Storage for AccountStorage {
         self.cache.insert(account_id, account);
     }
 }
+
+impl ReadableStorage for AccountStorage {
+    fn get(&self, account_id: AccountId) -> Option<Account> {
+        self.cache.get(&account_id).map(|account| account.clone())
+    }
+
+    fn scan(&self) -> impl Iterator<Item = Account> + Send {
+        //NOTE: Iterating the map would hold a shard lock between items, which the async adapter turns into awaits of the consumer
+        let account_ids: Vec<AccountId> = self.cache.iter().map(|account| *account.key()).collect();
+
+        account_ids.into_iter().filter_map(|account_id| self.cache.get(&account_id).map(|account| account.clone()))
+    }
+
+    fn count(&self) -> usize {
+        self.cache.len()
+    }
+}
@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+/// Failures raised by a storage backend while loading or saving an account.
+///
+/// The actors retry failed operations with a backoff, an error that persists past the last
+/// attempt is fatal to the engine. New variants may be added in minor releases.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum StorageError {
+    #[error("Storage I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Stored account could not be serialized: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite operation failed: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Stored account is corrupted: {message}")]
+    Corrupted {
+        message: String
+    },
+    #[error("Storage task failed: {message}")]
+    Unavailable {
+        message: String
+    }
+}
+
+impl StorageError {
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn corrupted(message: impl Into<String>) -> Self {
+        StorageError::Corrupted { message: message.into() }
+    }
+
+    pub(crate) fn unavailable(message: impl Into<String>) -> Self {
+        StorageError::Unavailable { message: message.into() }
+    }
+}
\ No newline at end of file
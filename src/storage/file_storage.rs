@@ -0,0 +1,346 @@
+use std::fs::{create_dir_all, rename, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{anyhow, Context, Result};
+use dashmap::iter::Iter;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use tokio_stream::Stream;
+use tracing::{debug, error, warn};
+
+use crate::models::{Account, AccountEvent};
+use crate::storage::{AsyncStorage, StorageError};
+use crate::types::AccountId;
+
+const SNAPSHOT_FILE: &str = "snapshot.jsonl";
+const SNAPSHOT_TEMPORARY_FILE: &str = "snapshot.jsonl.tmp";
+const WAL_FILE: &str = "wal.jsonl";
+
+/// Durable storage persisting every account to a directory on the local disk.
+///
+/// Every save is appended to a write-ahead log (one JSON record per line) before it becomes
+/// visible, and the log is periodically compacted into a snapshot holding the latest state of
+/// every account. A save extending the history of a stored account only logs the events added
+/// since, any other save logs the whole account. Opening the directory again replays the snapshot and the log, so a second run
+/// continues from where the first left off. A partially written last line of the log (e.g. after
+/// a crash) is ignored and dropped, while an unreadable record anywhere else, or anywhere in the
+/// snapshot, fails to open the storage.
+///
+/// The latest state of every account is also kept in memory to serve loads.
+pub struct FileStorage {
+    state: Arc<FileState>,
+    compaction_threshold: u64,
+    sync: bool
+}
+
+struct FileState {
+    directory: PathBuf,
+    cache: DashMap<AccountId, Account>,
+    wal: Mutex<WriteAheadLog>
+}
+
+struct WriteAheadLog {
+    file: File,
+    records: u64
+}
+
+/// A record of the write-ahead log or the snapshot.
+enum Record {
+    Account(Account),
+    Appended(Appended)
+}
+
+/// Events appended to the history of a stored account, ending with the entry hashed `after`.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Appended {
+    account_id: AccountId,
+    after: String,
+    events: Vec<AccountEvent>
+}
+
+impl Record {
+    fn parse(line: &str) -> serde_json::Result<Self> {
+        if let Ok(appended) = serde_json::from_str(line) {
+            return Ok(Self::Appended(appended))
+        }
+
+        serde_json::from_str(line).map(Self::Account)
+    }
+}
+
+impl Appended {
+    /// The events `account` adds to the history of `stored`, if its history extends the stored one.
+    ///
+    /// The state of an account with a history is the fold of that history, so replaying the events
+    /// onto the stored account reproduces `account` exactly.
+    fn since(stored: &Account, account: &Account) -> Option<Self> {
+        let last = stored.history.last()?;
+
+        //NOTE: Every hash covers the whole chain before it, so a matching hash means the stored history is a prefix
+        if account.history.get(stored.history.len() - 1).is_none_or(|entry| entry.hash != last.hash) {
+            return None
+        }
+
+        Some(Self {
+            account_id: account.account_id,
+            after: last.hash.clone(),
+            events: account.history[stored.history.len()..].iter().map(|entry| entry.event.clone()).collect()
+        })
+    }
+}
+
+impl FileStorage {
+    /// Opens (or creates) the storage directory and replays its content.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created or its files cannot be read.
+    pub fn open(directory: impl AsRef<Path>) -> Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        create_dir_all(&directory)
+            .with_context(|| format!("Error creating storage directory: {}", directory.display()))?;
+
+        let cache = DashMap::new();
+        //NOTE: The snapshot is only ever replaced by a rename, so it cannot be partially written
+        Self::replay(&directory.join(SNAPSHOT_FILE), &cache, false)?;
+        let (records, readable) = Self::replay(&directory.join(WAL_FILE), &cache, true)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(directory.join(WAL_FILE))
+            .with_context(|| format!("Error opening write-ahead log in: {}", directory.display()))?;
+
+        //NOTE: Drop a partially written last record, the next record would otherwise follow it and make it look corrupted
+        if file.metadata()?.len() > readable {
+            file.set_len(readable)?;
+        }
+
+        terminate_partial_line(&mut file)?;
+
+        debug!("Opened storage at [{}] with [{}] accounts", directory.display(), cache.len());
+
+        Ok(Self {
+            state: Arc::new(FileState {
+                directory,
+                cache,
+                wal: Mutex::new(WriteAheadLog { file, records })
+            }),
+            compaction_threshold: 10_000,
+            sync: false
+        })
+    }
+
+    /// Sets how many log records accumulate before the log is compacted into the snapshot.
+    pub fn with_compaction_threshold(mut self, threshold: u64) -> Self {
+        self.compaction_threshold = threshold.max(1);
+        self
+    }
+
+    /// Whether every save is synced to the disk, surviving power loss and not only process crashes.
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Iterates over the latest state of every account.
+    pub fn iter(&self) -> Iter<'_, AccountId, Account> {
+        self.state.cache.iter()
+    }
+
+    /// Rewrites the snapshot from the latest state of every account and truncates the log.
+    ///
+    /// # Errors
+    /// Returns an error if the snapshot cannot be written, the previous snapshot and log are kept intact.
+    pub fn compact(&self) -> Result<()> {
+        let mut wal = self.state.wal.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(self.state.compact_locked(&mut wal)?)
+    }
+
+    /// Applies every record of a JSON Lines file to the cache, returning the number of records read
+    /// and the length of the file up to the end of the last readable record.
+    ///
+    /// Only the last record of an appended file can be partially written by a crash, so with
+    /// `tolerate_partial_tail` an unreadable last record is skipped. Any other unreadable record,
+    /// or appended events that do not extend the history of their account, means the file is
+    /// corrupted and fails the replay.
+    fn replay(path: &Path, cache: &DashMap<AccountId, Account>, tolerate_partial_tail: bool) -> Result<(u64, u64)> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok((0, 0)),
+            Err(error) => return Err(error).with_context(|| format!("Error opening: {}", path.display()))
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let (mut records, mut number, mut offset, mut readable) = (0, 0, 0, 0);
+        let mut unreadable = None;
+
+        loop {
+            line.clear();
+
+            let read = reader.read_line(&mut line)?;
+
+            if read == 0 {
+                break;
+            }
+
+            number += 1;
+            offset += read as u64;
+
+            let record = line.trim_end_matches(['\n', '\r']);
+
+            if record.is_empty() {
+                continue;
+            }
+
+            if let Some((number, error)) = unreadable.take() {
+                return Err(anyhow!("Corrupted record at [{}] line [{number}], only the last record may be partially written: {error}", path.display()))
+            }
+
+            match Record::parse(record) {
+                Ok(Record::Account(mut account)) => {
+                    account.migrate_history();
+                    cache.insert(account.account_id, account);
+                    records += 1;
+                    readable = offset;
+                }
+                Ok(Record::Appended(appended)) => {
+                    let Some(mut account) = cache.get_mut(&appended.account_id)
+                        .filter(|account| account.history.last().is_some_and(|entry| entry.hash == appended.after)) else {
+                        return Err(anyhow!("Corrupted record at [{}] line [{number}]: events do not extend the history of client [{}]", path.display(), appended.account_id))
+                    };
+
+                    account.commit(&appended.events);
+                    records += 1;
+                    readable = offset;
+                }
+                Err(error) if tolerate_partial_tail => unreadable = Some((number, error)),
+                Err(error) => return Err(anyhow!("Corrupted record at [{}] line [{number}]: {error}", path.display()))
+            }
+        }
+
+        if let Some((number, error)) = unreadable {
+            warn!("Skipping partially written last record at [{}] line [{number}]: {error}", path.display());
+        }
+
+        Ok((records, readable))
+    }
+}
+
+impl FileState {
+    fn compact_locked(&self, wal: &mut WriteAheadLog) -> Result<(), StorageError> {
+        let temporary_path = self.directory.join(SNAPSHOT_TEMPORARY_FILE);
+        let mut writer = BufWriter::new(File::create(&temporary_path)?);
+
+        for account in self.cache.iter() {
+            serde_json::to_writer(&mut writer, account.value())?;
+            writeln!(writer)?;
+        }
+
+        let file = writer.into_inner().map_err(|error| error.into_error())?;
+        file.sync_all()?;
+        rename(&temporary_path, self.directory.join(SNAPSHOT_FILE))?;
+
+        wal.file.set_len(0)?;
+        wal.file.sync_all()?;
+        wal.records = 0;
+
+        debug!("Compacted storage at [{}] with [{}] accounts", self.directory.display(), self.cache.len());
+
+        Ok(())
+    }
+
+    fn append(wal: &mut WriteAheadLog, record: &impl Serialize, sync: bool) -> Result<(), StorageError> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        wal.file.write_all(&line)?;
+
+        if sync {
+            wal.file.sync_data()?;
+        }
+
+        wal.records += 1;
+
+        Ok(())
+    }
+
+    fn save(&self, account_id: AccountId, account: Account, compaction_threshold: u64, sync: bool) -> Result<(), StorageError> {
+        let mut wal = self.wal.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let appended = self.cache.get(&account_id).and_then(|stored| Appended::since(&stored, &account));
+
+        //NOTE: The account only becomes visible once it is durable, a failed append keeps the previous state
+        match appended {
+            //NOTE: Nothing was added to the history, so the state is unchanged too
+            Some(appended) if appended.events.is_empty() => {}
+            Some(appended) => Self::append(&mut wal, &appended, sync)?,
+            None => Self::append(&mut wal, &account, sync)?
+        }
+
+        self.cache.insert(account_id, account);
+
+        //NOTE: The record is already durable, a failed compaction is retried on the next save
+        if wal.records >= compaction_threshold
+            && let Err(error) = self.compact_locked(&mut wal) {
+            error!("Failed to compact storage at [{}]: {error}", self.directory.display());
+        }
+
+        Ok(())
+    }
+}
+
+impl AsyncStorage for FileStorage {
+    async fn load(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        Ok(self.state.cache.get(&account_id).map(|account| account.clone()))
+    }
+
+    async fn save(&self, account_id: AccountId, account: Account) -> Result<(), StorageError> {
+        let state = self.state.clone();
+        let (compaction_threshold, sync) = (self.compaction_threshold, self.sync);
+
+        spawn_blocking(move || state.save(account_id, account, compaction_threshold, sync))
+            .await
+            .map_err(|error| StorageError::unavailable(error.to_string()))?
+    }
+
+    async fn get(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        AsyncStorage::load(self, account_id).await
+    }
+
+    fn scan(&self) -> impl Stream<Item = Result<Account, StorageError>> + Send {
+        //NOTE: Iterating the map would hold a shard lock across the awaits of the consumer and block saves to that shard
+        let account_ids: Vec<AccountId> = self.state.cache.iter().map(|account| *account.key()).collect();
+        let cache = &self.state.cache;
+
+        tokio_stream::iter(account_ids.into_iter().filter_map(move |account_id| cache.get(&account_id).map(|account| Ok(account.clone()))))
+    }
+
+    async fn count(&self) -> Result<usize, StorageError> {
+        Ok(self.state.cache.len())
+    }
+}
+
+/// Ends a partially written last line so the next record starts on a line of its own.
+pub(crate) fn terminate_partial_line(file: &mut File) -> Result<()> {
+    let length = file.metadata()?.len();
+
+    if length == 0 {
+        return Ok(())
+    }
+
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(length - 1))?;
+    file.read_exact(&mut last)?;
+
+    if last[0] != b'\n' {
+        file.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
@@ -1,13 +1,88 @@
 mod account_storage;
+mod errors;
+mod file_storage;
+#[cfg(feature = "sqlite")]
+mod sqlite_storage;
 #[cfg(test)]
 mod tests;
 
+use std::future::Future;
+
+use tokio_stream::Stream;
+
 use crate::models::Account;
 use crate::types::AccountId;
 
 pub use account_storage::AccountStorage;
+pub use errors::StorageError;
+pub use file_storage::FileStorage;
+pub(crate) use file_storage::terminate_partial_line;
+#[cfg(feature = "sqlite")]
+pub use sqlite_storage::SqliteStorage;
 
+/// Persistence layer used by the actors to re-hydrate and passivate account state.
+///
+/// An actor calls `load` once when it is spawned and `save` once when it is passivated,
+/// so implementations only need to be safe for concurrent access across different clients.
+///
+/// This is the synchronous, infallible flavour meant for in-memory backends, every
+/// implementation that is also a [`ReadableStorage`] is an [`AsyncStorage`].
 pub trait Storage: Send + Sync + 'static {
+    /// Loads the last persisted state of the client, if any.
     fn load(&self, account_id: AccountId) -> Option<Account>;
+    /// Persists the state of the client, replacing any previous state.
     fn save(&self, account_id: AccountId, account: Account);
 }
+
+/// Non-destructive reads of a [`Storage`], used by output, reporting and admin tools.
+pub trait ReadableStorage: Storage {
+    /// Reads the persisted state of the client without taking ownership of it.
+    ///
+    /// Unlike `load`, this never evicts the account, so it is safe for output and admin tools.
+    fn get(&self, account_id: AccountId) -> Option<Account>;
+    /// Iterates over every persisted account, in no particular order.
+    fn scan(&self) -> impl Iterator<Item = Account> + Send;
+    /// The number of persisted accounts.
+    fn count(&self) -> usize;
+}
+
+/// Asynchronous, fallible persistence layer used by the engine.
+///
+/// Backends doing real I/O (disk, network) implement this trait directly so they never block
+/// the runtime and can report failures. A failed operation is retried by the actor with a
+/// backoff, and a failure that persists aborts the run with an error.
+pub trait AsyncStorage: Send + Sync + 'static {
+    /// Loads the last persisted state of the client, if any.
+    fn load(&self, account_id: AccountId) -> impl Future<Output = Result<Option<Account>, StorageError>> + Send;
+    /// Persists the state of the client, replacing any previous state.
+    fn save(&self, account_id: AccountId, account: Account) -> impl Future<Output = Result<(), StorageError>> + Send;
+    /// Reads the persisted state of the client without taking ownership of it, see [`ReadableStorage::get`].
+    fn get(&self, account_id: AccountId) -> impl Future<Output = Result<Option<Account>, StorageError>> + Send;
+    /// Streams every persisted account, in no particular order.
+    fn scan(&self) -> impl Stream<Item = Result<Account, StorageError>> + Send;
+    /// The number of persisted accounts.
+    fn count(&self) -> impl Future<Output = Result<usize, StorageError>> + Send;
+}
+
+impl<S: ReadableStorage> AsyncStorage for S {
+    async fn load(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        Ok(Storage::load(self, account_id))
+    }
+
+    async fn save(&self, account_id: AccountId, account: Account) -> Result<(), StorageError> {
+        Storage::save(self, account_id, account);
+        Ok(())
+    }
+
+    async fn get(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        Ok(ReadableStorage::get(self, account_id))
+    }
+
+    fn scan(&self) -> impl Stream<Item = Result<Account, StorageError>> + Send {
+        tokio_stream::iter(ReadableStorage::scan(self).map(Ok))
+    }
+
+    async fn count(&self) -> Result<usize, StorageError> {
+        Ok(ReadableStorage::count(self))
+    }
+}
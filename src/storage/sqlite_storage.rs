@@ -0,0 +1,469 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use tokio::sync::mpsc;
+use tokio::task::spawn_blocking;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tracing::debug;
+
+use crate::models::{Account, Balance, Currency, DisputeStatus, EntryKind, HistoryEntry, Hold, HoldStatus, LedgerEntry};
+use crate::storage::{AsyncStorage, StorageError};
+use crate::types::{AccountId, TransactionId};
+
+const SCAN_BUFFER: usize = 64;
+
+/// Ordered schema migrations, the schema version is the number of migrations applied.
+///
+/// Released migrations must never be edited, changes are appended as new migrations.
+const MIGRATIONS: &[&str] = &[
+    // 1: Accounts, deposit/withdrawal ledger and dispute status
+    "CREATE TABLE accounts (
+        client    INTEGER PRIMARY KEY,
+        available TEXT    NOT NULL,
+        held      TEXT    NOT NULL,
+        total     TEXT    NOT NULL,
+        locked    INTEGER NOT NULL
+    );
+    CREATE TABLE ledger (
+        client INTEGER NOT NULL REFERENCES accounts (client),
+        tx     INTEGER NOT NULL,
+        kind   TEXT    NOT NULL,
+        amount TEXT    NOT NULL,
+        PRIMARY KEY (client, tx)
+    );
+    CREATE TABLE disputes (
+        client INTEGER NOT NULL REFERENCES accounts (client),
+        tx     INTEGER NOT NULL,
+        status TEXT    NOT NULL,
+        PRIMARY KEY (client, tx)
+    );",
+    // 2: Hash chained history of events
+    "CREATE TABLE history (
+        client INTEGER NOT NULL REFERENCES accounts (client),
+        seq    INTEGER NOT NULL,
+        tx     INTEGER NOT NULL,
+        event  TEXT    NOT NULL,
+        hash   TEXT    NOT NULL,
+        PRIMARY KEY (client, seq)
+    );",
+    // 3: Balances per currency, the default balance stays in accounts
+    "ALTER TABLE ledger ADD COLUMN currency TEXT;
+    CREATE TABLE balances (
+        client    INTEGER NOT NULL REFERENCES accounts (client),
+        currency  TEXT    NOT NULL,
+        available TEXT    NOT NULL,
+        held      TEXT    NOT NULL,
+        total     TEXT    NOT NULL,
+        PRIMARY KEY (client, currency)
+    );",
+    // 4: Authorization holds, keyed by the ID of the authorization in the ledger
+    "CREATE TABLE holds (
+        client     INTEGER NOT NULL REFERENCES accounts (client),
+        tx         INTEGER NOT NULL,
+        status     TEXT    NOT NULL,
+        expires_at INTEGER,
+        PRIMARY KEY (client, tx)
+    );",
+    // 5: Amount and currency of holds, backfilled from the ledger entry of the authorization
+    "ALTER TABLE holds ADD COLUMN amount TEXT;
+    ALTER TABLE holds ADD COLUMN currency TEXT;
+    UPDATE holds SET
+        amount   = (SELECT amount FROM ledger WHERE ledger.client = holds.client AND ledger.tx = holds.tx),
+        currency = (SELECT currency FROM ledger WHERE ledger.client = holds.client AND ledger.tx = holds.tx);"
+];
+
+/// Storage backed by an embedded SQLite database.
+///
+/// Balances, the ledger, the dispute and hold status and the hash chained history of every account
+/// are stored in plain tables (`accounts`, `balances` per currency, `ledger`, `disputes`, `holds` and
+/// `history`, one JSON event per row) so the final state can be queried with SQL. Amounts are
+/// stored as exact decimal `TEXT`, use `CAST(available AS REAL)` for approximate aggregation.
+/// Every save is a single transaction, so an account is never persisted half way.
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>
+}
+
+impl SqliteStorage {
+    /// Opens (or creates) the database at `path` and applies any pending migration.
+    ///
+    /// # Errors
+    /// Returns an error if the database cannot be opened or migrated.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .with_context(|| format!("Error opening SQLite database at path: {}", path.display()))?;
+
+        Self::from_connection(connection)
+    }
+
+    /// Opens a private, in-memory database.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// The schema version of the database.
+    pub fn schema_version(&self) -> Result<u32> {
+        let connection = self.connection.lock().unwrap_or_else(PoisonError::into_inner);
+        Self::read_schema_version(&connection)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self> {
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        Self::migrate(&mut connection)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection))
+        })
+    }
+
+    fn migrate(connection: &mut Connection) -> Result<()> {
+        let version = Self::read_schema_version(connection)? as usize;
+
+        if version > MIGRATIONS.len() {
+            return Err(anyhow!("Database schema version [{version}] is newer than supported version [{}]", MIGRATIONS.len()))
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", (index + 1) as u32)?;
+            transaction.commit()?;
+
+            debug!("Applied SQLite migration [{}]", index + 1);
+        }
+
+        Ok(())
+    }
+
+    fn read_schema_version(connection: &Connection) -> Result<u32> {
+        Ok(connection.pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+
+    fn read_clients(connection: &Connection) -> Result<Vec<AccountId>, StorageError> {
+        let mut statement = connection.prepare("SELECT client FROM accounts ORDER BY client")?;
+        let clients = statement.query_map([], |row| row.get::<_, AccountId>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(clients)
+    }
+
+    fn read_account(connection: &Connection, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        let Some(mut account) = Self::read_state(connection, account_id)? else {
+            return Ok(None)
+        };
+
+        let mut statement = connection.prepare_cached("SELECT event, hash FROM history WHERE client = ?1 ORDER BY seq")?;
+        let mut rows = statement.query(params![account_id])?;
+
+        while let Some(row) = rows.next()? {
+            let event = serde_json::from_str(&row.get::<_, String>(0)?)?;
+            account.history.push(HistoryEntry { event, hash: row.get(1)? });
+        }
+
+        account.migrate_history();
+
+        Ok(Some(account))
+    }
+
+    /// Reads the balances, ledger, disputes and holds of an account, without its history.
+    fn read_state(connection: &Connection, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        let row = connection.query_row(
+            "SELECT available, held, locked FROM accounts WHERE client = ?1",
+            params![account_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        ).optional()?;
+
+        let Some((available, held, locked)) = row else {
+            return Ok(None)
+        };
+
+        let mut account = Account::new(account_id);
+        account.available = parse_amount(&available)?;
+        account.held = parse_amount(&held)?;
+        account.locked = locked;
+
+        let mut statement = connection.prepare_cached("SELECT currency, available, held FROM balances WHERE client = ?1")?;
+        let mut rows = statement.query(params![account_id])?;
+
+        while let Some(row) = rows.next()? {
+            let currency = parse_currency(&row.get::<_, String>(0)?)?;
+            let available = parse_amount(&row.get::<_, String>(1)?)?;
+            let held = parse_amount(&row.get::<_, String>(2)?)?;
+            account.currencies.insert(currency, Balance { available, held });
+        }
+
+        let mut statement = connection.prepare_cached("SELECT tx, kind, amount, currency FROM ledger WHERE client = ?1")?;
+        let mut rows = statement.query(params![account_id])?;
+
+        while let Some(row) = rows.next()? {
+            let kind = parse_entry_kind(&row.get::<_, String>(1)?)?;
+            let amount = parse_amount(&row.get::<_, String>(2)?)?;
+            let currency = row.get::<_, Option<String>>(3)?.as_deref().map(parse_currency).transpose()?;
+            account.ledger.insert(row.get::<_, TransactionId>(0)?, LedgerEntry { kind, amount, currency });
+        }
+
+        let mut statement = connection.prepare_cached("SELECT tx, status FROM disputes WHERE client = ?1")?;
+        let mut rows = statement.query(params![account_id])?;
+
+        while let Some(row) = rows.next()? {
+            let status = parse_dispute_status(&row.get::<_, String>(1)?)?;
+            account.disputes.insert(row.get::<_, TransactionId>(0)?, status);
+        }
+
+        let mut statement = connection.prepare_cached("SELECT tx, status, amount, currency, expires_at FROM holds WHERE client = ?1")?;
+        let mut rows = statement.query(params![account_id])?;
+
+        while let Some(row) = rows.next()? {
+            let status = parse_hold_status(&row.get::<_, String>(1)?)?;
+            let amount = parse_amount(&row.get::<_, String>(2)?)?;
+            let currency = row.get::<_, Option<String>>(3)?.as_deref().map(parse_currency).transpose()?;
+            let expires_at = row.get::<_, Option<i64>>(4)?.map(|expires_at| expires_at as u64);
+            account.holds.insert(row.get::<_, TransactionId>(0)?, Hold { status, amount, currency, expires_at });
+        }
+
+        Ok(Some(account))
+    }
+
+    /// Writes the rows of the account that differ from the stored ones and deletes those it no longer has.
+    fn write_account(connection: &mut Connection, account: &Account) -> Result<(), StorageError> {
+        let transaction = connection.transaction()?;
+        let account_id = account.account_id;
+        let stored = Self::read_state(&transaction, account_id)?;
+
+        if stored.as_ref().is_none_or(|stored| (stored.available, stored.held, stored.locked) != (account.available, account.held, account.locked)) {
+            transaction.execute(
+                "INSERT INTO accounts (client, available, held, total, locked) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (client) DO UPDATE SET available = ?2, held = ?3, total = ?4, locked = ?5",
+                params![account_id, account.available.to_string(), account.held.to_string(), account.total().to_string(), account.locked]
+            )?;
+        }
+
+        let stored = stored.unwrap_or_else(|| Account::new(account_id));
+
+        {
+            let mut statement = transaction.prepare_cached("INSERT OR REPLACE INTO balances (client, currency, available, held, total) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+
+            for (currency, balance) in account.currencies.iter().filter(|(currency, balance)| stored.currencies.get(*currency) != Some(*balance)) {
+                statement.execute(params![account_id, currency.as_str(), balance.available.to_string(), balance.held.to_string(), balance.total().to_string()])?;
+            }
+
+            let mut statement = transaction.prepare_cached("DELETE FROM balances WHERE client = ?1 AND currency = ?2")?;
+
+            for currency in stored.currencies.keys().filter(|currency| !account.currencies.contains_key(*currency)) {
+                statement.execute(params![account_id, currency.as_str()])?;
+            }
+
+            let mut statement = transaction.prepare_cached("INSERT OR REPLACE INTO ledger (client, tx, kind, amount, currency) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+
+            for (transaction_id, entry) in account.ledger.iter().filter(|(transaction_id, entry)| stored.ledger.get(*transaction_id) != Some(*entry)) {
+                let currency = entry.currency.as_ref().map(Currency::as_str);
+                statement.execute(params![account_id, transaction_id, entry_kind_name(entry.kind), entry.amount.to_string(), currency])?;
+            }
+
+            let mut statement = transaction.prepare_cached("DELETE FROM ledger WHERE client = ?1 AND tx = ?2")?;
+
+            for transaction_id in stored.ledger.keys().filter(|transaction_id| !account.ledger.contains_key(*transaction_id)) {
+                statement.execute(params![account_id, transaction_id])?;
+            }
+
+            let mut statement = transaction.prepare_cached("INSERT OR REPLACE INTO disputes (client, tx, status) VALUES (?1, ?2, ?3)")?;
+
+            for (transaction_id, status) in account.disputes.iter().filter(|(transaction_id, status)| stored.disputes.get(*transaction_id) != Some(*status)) {
+                statement.execute(params![account_id, transaction_id, dispute_status_name(status)])?;
+            }
+
+            let mut statement = transaction.prepare_cached("DELETE FROM disputes WHERE client = ?1 AND tx = ?2")?;
+
+            for transaction_id in stored.disputes.keys().filter(|transaction_id| !account.disputes.contains_key(*transaction_id)) {
+                statement.execute(params![account_id, transaction_id])?;
+            }
+
+            let mut statement = transaction.prepare_cached("INSERT OR REPLACE INTO holds (client, tx, status, amount, currency, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+
+            for (transaction_id, hold) in account.holds.iter().filter(|(transaction_id, hold)| stored.holds.get(*transaction_id) != Some(*hold)) {
+                let currency = hold.currency.as_ref().map(Currency::as_str);
+                //NOTE: SQLite integers are signed, the cast round-trips even the (absurd) expiries past i64::MAX
+                let expires_at = hold.expires_at.map(|expires_at| expires_at as i64);
+                statement.execute(params![account_id, transaction_id, hold_status_name(hold.status), hold.amount.to_string(), currency, expires_at])?;
+            }
+
+            let mut statement = transaction.prepare_cached("DELETE FROM holds WHERE client = ?1 AND tx = ?2")?;
+
+            for transaction_id in stored.holds.keys().filter(|transaction_id| !account.holds.contains_key(*transaction_id)) {
+                statement.execute(params![account_id, transaction_id])?;
+            }
+
+            //NOTE: Every hash covers the entries before it, so the saved history extends the stored one exactly when it holds
+            //      the hash of the last stored entry at the same sequence. Otherwise (e.g. the account was restored from a
+            //      snapshot over an existing database) the stored history is replaced rather than spliced
+            let last: Option<(i64, String)> = transaction.query_row(
+                "SELECT seq, hash FROM history WHERE client = ?1 ORDER BY seq DESC LIMIT 1",
+                params![account_id],
+                |row| Ok((row.get(0)?, row.get(1)?))
+            ).optional()?;
+
+            let stored = match last {
+                None => 0,
+                Some((sequence, hash)) if account.history.get(sequence as usize).is_some_and(|entry| entry.hash == hash) => sequence as usize + 1,
+                Some(_) => {
+                    debug!("Replacing the diverging stored history of client [{account_id}]");
+                    transaction.execute("DELETE FROM history WHERE client = ?1", params![account_id])?;
+                    0
+                }
+            };
+
+            let mut statement = transaction.prepare_cached("INSERT INTO history (client, seq, tx, event, hash) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+
+            for (sequence, entry) in account.history.iter().enumerate().skip(stored) {
+                statement.execute(params![account_id, sequence as i64, entry.event.transaction_id(), serde_json::to_string(&entry.event)?, entry.hash])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+impl AsyncStorage for SqliteStorage {
+    async fn load(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        let connection = self.connection.clone();
+
+        spawn_blocking(move || {
+            let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+            Self::read_account(&connection, account_id)
+        })
+        .await
+        .map_err(|error| StorageError::unavailable(error.to_string()))?
+    }
+
+    async fn save(&self, _account_id: AccountId, account: Account) -> Result<(), StorageError> {
+        let connection = self.connection.clone();
+
+        spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+            Self::write_account(&mut connection, &account)
+        })
+        .await
+        .map_err(|error| StorageError::unavailable(error.to_string()))?
+    }
+
+    async fn get(&self, account_id: AccountId) -> Result<Option<Account>, StorageError> {
+        AsyncStorage::load(self, account_id).await
+    }
+
+    fn scan(&self) -> impl Stream<Item = Result<Account, StorageError>> + Send {
+        let connection = self.connection.clone();
+        let (sender, receiver) = mpsc::channel(SCAN_BUFFER);
+
+        //NOTE: Accounts are read one at a time on the blocking pool, the bounded channel keeps memory flat for large databases.
+        //      The connection is locked per account and never across a send, so the consumer can load and save while draining
+        //      the stream, and the scan only holds off saves for the duration of a single read
+        spawn_blocking(move || {
+            let clients = Self::read_clients(&connection.lock().unwrap_or_else(PoisonError::into_inner));
+
+            let clients = match clients {
+                Ok(clients) => clients,
+                Err(error) => {
+                    let _ = sender.blocking_send(Err(error));
+                    return;
+                }
+            };
+
+            for client in clients {
+                let account = Self::read_account(&connection.lock().unwrap_or_else(PoisonError::into_inner), client).transpose();
+
+                if let Some(account) = account && sender.blocking_send(account).is_err() {
+                    return;
+                }
+            }
+        });
+
+        ReceiverStream::new(receiver)
+    }
+
+    async fn count(&self) -> Result<usize, StorageError> {
+        let connection = self.connection.clone();
+
+        spawn_blocking(move || {
+            let connection = connection.lock().unwrap_or_else(PoisonError::into_inner);
+            let count: i64 = connection.query_row("SELECT COUNT(*) FROM accounts", [], |row| row.get(0))?;
+
+            Ok(count as usize)
+        })
+        .await
+        .map_err(|error| StorageError::unavailable(error.to_string()))?
+    }
+}
+
+fn entry_kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Deposit => "deposit",
+        EntryKind::Withdrawal => "withdrawal",
+        EntryKind::TransferOut => "transfer_out",
+        EntryKind::TransferIn => "transfer_in",
+        EntryKind::Authorization => "authorization"
+    }
+}
+
+fn parse_entry_kind(name: &str) -> Result<EntryKind, StorageError> {
+    match name {
+        "deposit" => Ok(EntryKind::Deposit),
+        "withdrawal" => Ok(EntryKind::Withdrawal),
+        "transfer_out" => Ok(EntryKind::TransferOut),
+        "transfer_in" => Ok(EntryKind::TransferIn),
+        "authorization" => Ok(EntryKind::Authorization),
+        _ => Err(StorageError::corrupted(format!("Unknown ledger entry kind '{name}'")))
+    }
+}
+
+fn dispute_status_name(status: &DisputeStatus) -> &'static str {
+    match status {
+        DisputeStatus::InProgress => "in_progress",
+        DisputeStatus::Resolved => "resolved",
+        DisputeStatus::Chargeback => "chargeback"
+    }
+}
+
+fn parse_dispute_status(name: &str) -> Result<DisputeStatus, StorageError> {
+    match name {
+        "in_progress" => Ok(DisputeStatus::InProgress),
+        "resolved" => Ok(DisputeStatus::Resolved),
+        "chargeback" => Ok(DisputeStatus::Chargeback),
+        _ => Err(StorageError::corrupted(format!("Unknown dispute status '{name}'")))
+    }
+}
+
+fn hold_status_name(status: HoldStatus) -> &'static str {
+    match status {
+        HoldStatus::Open => "open",
+        HoldStatus::Captured => "captured",
+        HoldStatus::Released => "released",
+        HoldStatus::Expired => "expired"
+    }
+}
+
+fn parse_hold_status(name: &str) -> Result<HoldStatus, StorageError> {
+    match name {
+        "open" => Ok(HoldStatus::Open),
+        "captured" => Ok(HoldStatus::Captured),
+        "released" => Ok(HoldStatus::Released),
+        "expired" => Ok(HoldStatus::Expired),
+        _ => Err(StorageError::corrupted(format!("Unknown hold status '{name}'")))
+    }
+}
+
+fn parse_amount(amount: &str) -> Result<Decimal, StorageError> {
+    Decimal::from_str(amount).map_err(|error| StorageError::corrupted(format!("Invalid amount '{amount}': {error}")))
+}
+
+fn parse_currency(currency: &str) -> Result<Currency, StorageError> {
+    Currency::from_str(currency).map_err(|error| StorageError::corrupted(error.to_string()))
+}
\ No newline at end of file
@@ -1,11 +1,14 @@
-use super::{AccountStorage, Storage};
+use super::{AccountStorage, FileStorage, ReadableStorage, Storage};
 
+use std::fs::{read_to_string, write, OpenOptions};
+use std::io::Write;
 use std::str::FromStr;
 
 use anyhow::{anyhow, Result};
 use rust_decimal::Decimal;
+use tempfile::tempdir;
 
-use crate::models::Account;
+use crate::models::{Account, Transaction};
 
 #[test]
 fn test_storage_basic_load_and_save_operations() -> Result<()> {
@@ -52,4 +55,614 @@ fn test_storage_enforces_correct_overwrite_semantics() -> Result<()> {
     assert_eq!(final_account.available, Decimal::from_str("20.0")?);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_storage_get_scan_and_count_do_not_evict() {
+    let storage = AccountStorage::new();
+    storage.save(1, Account::new(1));
+    storage.save(2, Account::new(2));
+
+    assert_eq!(storage.get(1).map(|account| account.account_id), Some(1));
+    assert!(storage.get(3).is_none());
+
+    let mut clients: Vec<_> = storage.scan().map(|account| account.account_id).collect();
+    clients.sort();
+
+    assert_eq!(clients, vec![1, 2]);
+    assert_eq!(storage.count(), 2);
+    assert!(storage.load(1).is_some(), "get and scan must leave the account in place");
+    assert_eq!(storage.count(), 1);
+}
+
+#[tokio::test]
+async fn test_file_storage_scans_and_counts_the_latest_state() -> Result<()> {
+    use tokio_stream::StreamExt;
+
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    for account_id in 1..=3 {
+        storage.save(account_id, Account::new(account_id)).await?;
+    }
+
+    let mut updated = Account::new(2);
+    updated.available = Decimal::TEN;
+    storage.save(2, updated).await?;
+
+    let accounts: Vec<Account> = storage.scan().collect::<Result<_, _>>().await?;
+
+    assert_eq!(accounts.len(), 3);
+    assert_eq!(storage.count().await?, 3);
+    assert_eq!(storage.get(2).await?.map(|account| account.available), Some(Decimal::TEN));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_storage_saves_while_a_scan_is_drained() -> Result<()> {
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tokio_stream::StreamExt;
+
+    use super::AsyncStorage;
+
+    let storage = Arc::new(AccountStorage::new());
+
+    for account_id in 1..=100 {
+        Storage::save(storage.as_ref(), account_id, Account::new(account_id));
+    }
+
+    let mut accounts = Box::pin(AsyncStorage::scan(storage.as_ref()));
+    accounts.next().await.transpose()?;
+
+    //NOTE: The save blocks its thread while a shard is locked, so it runs on a thread of its own
+    let (sender, receiver) = mpsc::channel();
+    let saving = storage.clone();
+    thread::spawn(move || {
+        for account_id in 1..=100 {
+            Storage::save(saving.as_ref(), account_id, Account::new(account_id));
+        }
+
+        let _ = sender.send(());
+    });
+
+    receiver.recv_timeout(Duration::from_secs(10))?;
+
+    assert_eq!(accounts.collect::<Result<Vec<_>, _>>().await?.len(), 99);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_saves_while_a_scan_is_drained() -> Result<()> {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    for account_id in 1..=100 {
+        storage.save(account_id, Account::new(account_id)).await?;
+    }
+
+    let mut accounts = Box::pin(storage.scan());
+    accounts.next().await.transpose()?;
+
+    timeout(Duration::from_secs(10), async {
+        for account_id in 1..=100 {
+            storage.save(account_id, Account::new(account_id)).await?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    }).await??;
+
+    assert_eq!(accounts.collect::<Result<Vec<_>, _>>().await?.len(), 99);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_persists_accounts_across_reopen() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::from_str("10.0")?))?;
+    account.apply(&Transaction::dispute(1, 1))?;
+
+    FileStorage::open(directory.path())?.save(1, account).await?;
+
+    let storage = FileStorage::open(directory.path())?;
+    let mut reloaded = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+
+    assert_eq!(reloaded.held, Decimal::from_str("10.0")?);
+    assert!(storage.load(1).await?.is_some(), "loading must not remove durable state");
+
+    // The ledger and disputes survive too, so the dispute can still be resolved
+    reloaded.apply(&Transaction::resolve(1, 1))?;
+
+    assert_eq!(reloaded.available, Decimal::from_str("10.0")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_logs_only_the_history_added_since_the_last_save() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    let mut account = Account::new(1);
+
+    for transaction_id in 1..=100 {
+        account.apply(&Transaction::deposit(1, transaction_id, Decimal::from_str("1.0")?))?;
+    }
+
+    storage.save(1, account.clone()).await?;
+
+    account.apply(&Transaction::deposit(1, 101, Decimal::from_str("1.0")?))?;
+    account.apply(&Transaction::dispute(1, 101))?;
+    storage.save(1, account.clone()).await?;
+
+    // Saving an unchanged account logs nothing
+    storage.save(1, account.clone()).await?;
+
+    let log = read_to_string(directory.path().join("wal.jsonl"))?;
+    let lines: Vec<&str> = log.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].len() * 10 < lines[0].len(), "the second record must not repeat the persisted history");
+    assert!(!lines[1].contains("\"transaction_id\":100,"));
+
+    let reopened = FileStorage::open(directory.path())?;
+    let reloaded = reopened.load(1).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+
+    assert_eq!(reloaded, account);
+    assert!(reloaded.verify().is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_refuses_events_that_do_not_extend_the_stored_history() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::from_str("10.0")?))?;
+    storage.save(1, account.clone()).await?;
+
+    account.apply(&Transaction::deposit(1, 2, Decimal::from_str("5.0")?))?;
+    storage.save(1, account).await?;
+    drop(storage);
+
+    let path = directory.path().join("wal.jsonl");
+    let mut lines: Vec<String> = read_to_string(&path)?.lines().map(String::from).collect();
+    lines.swap(0, 1);
+    write(&path, lines.join("\n") + "\n")?;
+
+    let error = FileStorage::open(directory.path()).err().ok_or_else(|| anyhow!("Opened a corrupted log"))?;
+
+    assert!(error.to_string().contains("line [1]"));
+    assert!(error.to_string().contains("client [1]"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_migrates_accounts_persisted_without_history() -> Result<()> {
+    use super::AsyncStorage;
+    use crate::audit::Verification;
+    use crate::models::AccountEvent;
+
+    let directory = tempdir()?;
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::from_str("10.0")?))?;
+    account.apply(&Transaction::dispute(1, 1))?;
+
+    // Accounts were persisted without a history before it existed
+    let mut legacy = serde_json::to_value(&account)?;
+    legacy.as_object_mut().ok_or_else(|| anyhow!("Account is not an object"))?.remove("history");
+    writeln!(OpenOptions::new().create(true).append(true).open(directory.path().join("wal.jsonl"))?, "{legacy}")?;
+
+    let storage = FileStorage::open(directory.path())?;
+    let verification = Verification::run(&storage).await?;
+
+    assert_eq!(verification.verified, 1);
+    assert!(verification.is_intact());
+
+    let mut migrated = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+
+    assert!(matches!(migrated.history()[0].event(), AccountEvent::Migrated { .. }));
+    assert_eq!(migrated.held, Decimal::from_str("10.0")?);
+
+    // The history continues from the migrated state
+    migrated.apply(&Transaction::resolve(1, 1))?;
+
+    assert!(migrated.verify().is_ok());
+    assert_eq!(Account::from_events(1, migrated.history().iter().map(|entry| entry.event())), migrated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_compacts_the_write_ahead_log() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?
+        .with_compaction_threshold(3);
+
+    for version in 1..=4 {
+        let mut account = Account::new(1);
+        account.available = Decimal::from(version);
+        storage.save(1, account).await?;
+    }
+
+    assert_eq!(read_to_string(directory.path().join("wal.jsonl"))?.lines().count(), 1);
+    assert_eq!(read_to_string(directory.path().join("snapshot.jsonl"))?.lines().count(), 1);
+    assert_eq!(FileStorage::open(directory.path())?.load(1).await?.unwrap().available, Decimal::from(4));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_ignores_a_partially_written_record() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    FileStorage::open(directory.path())?.save(1, Account::new(1)).await?;
+
+    let mut wal = OpenOptions::new().append(true).open(directory.path().join("wal.jsonl"))?;
+    write!(wal, "{{\"account_id\":2,\"avail")?;
+    drop(wal);
+
+    let storage = FileStorage::open(directory.path())?;
+    storage.save(3, Account::new(3)).await?;
+
+    let reopened = FileStorage::open(directory.path())?;
+
+    assert!(reopened.load(1).await?.is_some());
+    assert!(reopened.load(2).await?.is_none());
+    assert!(reopened.load(3).await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_refuses_a_corrupted_record_before_the_last() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    for account_id in 1..=3 {
+        storage.save(account_id, Account::new(account_id)).await?;
+    }
+
+    drop(storage);
+
+    let path = directory.path().join("wal.jsonl");
+    let mut lines: Vec<String> = read_to_string(&path)?.lines().map(String::from).collect();
+    lines[1].truncate(10);
+    write(&path, lines.join("\n") + "\n")?;
+
+    let error = FileStorage::open(directory.path()).err().ok_or_else(|| anyhow!("Opened a corrupted log"))?;
+
+    assert!(error.to_string().contains("line [2]"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_storage_refuses_a_corrupted_last_snapshot_record() -> Result<()> {
+    use super::AsyncStorage;
+
+    let directory = tempdir()?;
+    let storage = FileStorage::open(directory.path())?;
+
+    for account_id in 1..=2 {
+        storage.save(account_id, Account::new(account_id)).await?;
+    }
+
+    storage.compact()?;
+    drop(storage);
+
+    let path = directory.path().join("snapshot.jsonl");
+    let mut lines: Vec<String> = read_to_string(&path)?.lines().map(String::from).collect();
+    lines[1].truncate(10);
+    write(&path, lines.join("\n"))?;
+
+    let error = FileStorage::open(directory.path()).err().ok_or_else(|| anyhow!("Opened a corrupted snapshot"))?;
+
+    assert!(error.to_string().contains("snapshot.jsonl"));
+    assert!(error.to_string().contains("line [2]"));
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_persists_accounts_across_reopen() -> Result<()> {
+    use tokio_stream::StreamExt;
+
+    use super::{AsyncStorage, SqliteStorage};
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+
+    let mut account = Account::new(1);
+    account.apply(&Transaction::deposit(1, 1, Decimal::from_str("10.0")?))?;
+    account.apply(&Transaction::withdrawal(1, 2, Decimal::from_str("2.5")?))?;
+    account.apply(&Transaction::dispute(1, 1))?;
+
+    SqliteStorage::open(&path)?.save(1, account).await?;
+
+    let storage = SqliteStorage::open(&path)?;
+    let mut reloaded = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+
+    assert_eq!(reloaded.available, Decimal::from_str("-2.5")?);
+    assert_eq!(reloaded.held, Decimal::from_str("10.0")?);
+    assert!(storage.load(1).await?.is_some(), "loading must not remove durable state");
+    assert!(storage.load(2).await?.is_none());
+
+    reloaded.apply(&Transaction::chargeback(1, 1))?;
+    storage.save(1, reloaded).await?;
+
+    let accounts: Vec<Account> = storage.scan().collect::<Result<_, _>>().await?;
+
+    assert_eq!(storage.count().await?, 1);
+    assert_eq!(accounts.len(), 1);
+    assert!(accounts[0].is_locked());
+    assert_eq!(accounts[0].total(), Decimal::from_str("-2.5")?);
+
+    // The history saved in two parts is read back as one intact chain
+    assert_eq!(accounts[0].history().len(), 5);
+    assert!(accounts[0].verify().is_ok());
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_tables_are_queryable_with_sql() -> Result<()> {
+    use rusqlite::Connection;
+
+    use super::{AsyncStorage, SqliteStorage};
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+    let storage = SqliteStorage::open(&path)?;
+
+    let mut account = Account::new(7);
+    account.apply(&Transaction::deposit(7, 1, Decimal::from_str("1.5")?))?;
+    account.apply(&Transaction::deposit(7, 2, Decimal::from_str("2.0")?))?;
+    account.apply(&Transaction::dispute(7, 2))?;
+    storage.save(7, account).await?;
+
+    let connection = Connection::open(&path)?;
+    let (available, total): (String, String) = connection.query_row(
+        "SELECT available, total FROM accounts WHERE client = 7", [], |row| Ok((row.get(0)?, row.get(1)?))
+    )?;
+    let deposits: u32 = connection.query_row("SELECT COUNT(*) FROM ledger WHERE kind = 'deposit'", [], |row| row.get(0))?;
+    let status: String = connection.query_row("SELECT status FROM disputes WHERE client = 7 AND tx = 2", [], |row| row.get(0))?;
+
+    assert_eq!(available, "1.5");
+    assert_eq!(total, "3.5");
+    assert_eq!(deposits, 2);
+    assert_eq!(status, "in_progress");
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_persists_balances_per_currency() -> Result<()> {
+    use rusqlite::Connection;
+
+    use super::{AsyncStorage, SqliteStorage};
+    use crate::models::Currency;
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+    let usd = Currency::from_str("usd")?;
+
+    let mut account = Account::new(3);
+    account.apply(&Transaction::deposit(3, 1, Decimal::from_str("1.0")?))?;
+    account.apply(&Transaction::deposit(3, 2, Decimal::from_str("5.0")?).with_currency(usd.clone()))?;
+    account.apply(&Transaction::dispute(3, 2))?;
+    account.apply(&Transaction::transfer(3, 4, 3, Decimal::from_str("0.5")?))?;
+
+    SqliteStorage::open(&path)?.save(3, account.clone()).await?;
+
+    let reloaded = SqliteStorage::open(&path)?.load(3).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+    let held: String = Connection::open(&path)?.query_row("SELECT held FROM balances WHERE client = 3 AND currency = 'USD'", [], |row| row.get(0))?;
+
+    assert_eq!(reloaded, account);
+    assert_eq!(reloaded.balance(Some(&usd)).held(), Decimal::from_str("5.0")?);
+    assert!(reloaded.verify().is_ok());
+    assert_eq!(held, "5.0");
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_persists_authorization_holds() -> Result<()> {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use rusqlite::Connection;
+
+    use super::{AsyncStorage, SqliteStorage};
+    use crate::models::AccountPolicy;
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+    let policy = AccountPolicy::default().with_hold_expiry(Duration::from_secs(60));
+    let now = UNIX_EPOCH + Duration::from_secs(1_000);
+
+    let mut account = Account::new(5);
+    account.apply_at(&Transaction::deposit(5, 1, Decimal::from_str("10.0")?), &policy, now)?;
+    account.apply_at(&Transaction::authorize(5, 2, Decimal::from_str("3.0")?), &policy, now)?;
+    account.apply_at(&Transaction::authorize(5, 3, Decimal::from_str("2.0")?), &policy, now)?;
+    account.apply_at(&Transaction::capture(5, 2), &policy, now)?;
+
+    SqliteStorage::open(&path)?.save(5, account.clone()).await?;
+
+    let reloaded = SqliteStorage::open(&path)?.load(5).await?.ok_or_else(|| anyhow!("Account missing after reopen"))?;
+    let (status, expires_at): (String, i64) = Connection::open(&path)?.query_row(
+        "SELECT status, expires_at FROM holds WHERE client = 5 AND tx = 3", [], |row| Ok((row.get(0)?, row.get(1)?))
+    )?;
+
+    assert_eq!(reloaded, account);
+    assert!(reloaded.verify().is_ok());
+    assert_eq!(status, "open");
+    assert_eq!(expires_at, 1_060);
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_saves_while_a_scan_is_drained() -> Result<()> {
+    use std::pin::pin;
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+
+    use super::{AsyncStorage, SqliteStorage};
+
+    let storage = SqliteStorage::open_in_memory()?;
+
+    for account_id in 1..=200 {
+        storage.save(account_id, Account::new(account_id)).await?;
+    }
+
+    //NOTE: More accounts than the scan buffers, so a scan holding the connection across sends would never let the save through
+    let scanned = timeout(Duration::from_secs(30), async {
+        let mut accounts = pin!(storage.scan());
+        let mut scanned = 0;
+
+        while let Some(account) = accounts.next().await {
+            let mut account = account?;
+            account.apply(&Transaction::deposit(account.account_id, 1, Decimal::ONE))?;
+            storage.save(account.account_id, account).await?;
+            scanned += 1;
+        }
+
+        Ok::<_, anyhow::Error>(scanned)
+    }).await??;
+
+    assert_eq!(scanned, 200);
+    assert_eq!(storage.get(200).await?.map(|account| account.available), Some(Decimal::ONE));
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_replaces_a_diverging_history() -> Result<()> {
+    use rusqlite::Connection;
+
+    use super::{AsyncStorage, SqliteStorage};
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+    let storage = SqliteStorage::open(&path)?;
+
+    let mut stored = Account::new(1);
+    stored.apply(&Transaction::deposit(1, 1, Decimal::TEN))?;
+    storage.save(1, stored).await?;
+
+    //NOTE: E.g. an account restored from a snapshot, its history does not extend the stored one
+    let mut restored = Account::new(1);
+    restored.apply(&Transaction::deposit(1, 2, Decimal::from(5)))?;
+    restored.apply(&Transaction::deposit(1, 3, Decimal::from(5)))?;
+    storage.save(1, restored.clone()).await?;
+
+    let reloaded = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after save"))?;
+    let ledger: u32 = Connection::open(&path)?.query_row("SELECT COUNT(*) FROM ledger WHERE client = 1", [], |row| row.get(0))?;
+
+    assert_eq!(reloaded, restored);
+    assert!(reloaded.verify().is_ok());
+    assert_eq!(ledger, 2);
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_storage_only_writes_changed_rows() -> Result<()> {
+    use rusqlite::Connection;
+
+    use super::{AsyncStorage, SqliteStorage};
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+    let storage = SqliteStorage::open(&path)?;
+
+    Connection::open(&path)?.execute_batch(
+        "CREATE TABLE ledger_writes (client INTEGER NOT NULL, tx INTEGER NOT NULL);
+         CREATE TRIGGER count_ledger_writes AFTER INSERT ON ledger BEGIN
+             INSERT INTO ledger_writes (client, tx) VALUES (new.client, new.tx);
+         END;"
+    )?;
+
+    let mut account = Account::new(1);
+
+    for transaction_id in 1..=3 {
+        account.apply(&Transaction::deposit(1, transaction_id, Decimal::ONE))?;
+    }
+
+    storage.save(1, account).await?;
+
+    let mut account = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after save"))?;
+    account.apply(&Transaction::deposit(1, 4, Decimal::ONE))?;
+    storage.save(1, account).await?;
+
+    let writes: u32 = Connection::open(&path)?.query_row("SELECT COUNT(*) FROM ledger_writes", [], |row| row.get(0))?;
+    let reloaded = storage.load(1).await?.ok_or_else(|| anyhow!("Account missing after save"))?;
+
+    assert_eq!(writes, 4);
+    assert_eq!(reloaded.available, Decimal::from(4));
+    assert!(reloaded.verify().is_ok());
+
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_storage_migrates_once_and_rejects_newer_schemas() -> Result<()> {
+    use rusqlite::Connection;
+
+    use super::SqliteStorage;
+
+    let directory = tempdir()?;
+    let path = directory.path().join("state.db");
+
+    assert_eq!(SqliteStorage::open(&path)?.schema_version()?, 5);
+    assert_eq!(SqliteStorage::open(&path)?.schema_version()?, 5);
+    assert_eq!(SqliteStorage::open_in_memory()?.schema_version()?, 5);
+
+    Connection::open(&path)?.pragma_update(None, "user_version", 99)?;
+
+    assert!(SqliteStorage::open(&path).is_err());
+
+    Ok(())
+}
@@ -1,7 +1,12 @@
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use std::path::Path;
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 use anyhow::{anyhow, Result};
+use async_transaction_engine::{Account, AccountError, AccountStorage, AsyncEngine, Storage, Transaction};
+use rust_decimal::Decimal;
 
 #[test]
 fn test_cli_correctly_processes_sample() -> Result<()> {
@@ -34,6 +39,31 @@ fn test_cli_correctly_processes_sample() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_cli_accepts_a_trailing_log_level_and_refuses_missing_inputs() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+    let sample_path = Path::new("samples").join("sample.csv");
+
+    let output = Command::new(binary_path)
+        .arg(&sample_path)
+        .arg("debug")
+        .output()?;
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.starts_with("client,available,held,total,locked"));
+    assert!(String::from_utf8(output.stderr)?.contains("--log-level"));
+
+    let output = Command::new(binary_path)
+        .arg(&sample_path)
+        .arg("missing.csv")
+        .output()?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("missing.csv"));
+
+    Ok(())
+}
+
 #[test]
 fn test_cli_outputs_correct_final_balances() -> Result<()> {
     let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
@@ -70,3 +100,294 @@ fn test_cli_outputs_correct_final_balances() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_cli_writes_sorted_output_in_the_requested_format() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+    let directory = tempfile::tempdir()?;
+    let output_path = directory.path().join("accounts.json");
+
+    let output = Command::new(binary_path)
+        .arg(Path::new("samples").join("fixed.csv"))
+        .arg("--output")
+        .arg(&output_path)
+        .output()?;
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let accounts: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(&output_path)?)?;
+    let clients: Vec<u64> = accounts.iter().filter_map(|account| account["client"].as_u64()).collect();
+
+    assert_eq!(clients, vec![1, 2]);
+    assert_eq!(accounts[0]["available"], "25.0000");
+
+    let output = Command::new(binary_path)
+        .arg(Path::new("samples").join("fixed.csv"))
+        .args(["--output-format", "jsonl", "--sort", "total"])
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, concat!(
+        "{\"client\":1,\"available\":\"25.0000\",\"held\":\"0.0000\",\"total\":\"25.0000\",\"locked\":false}\n",
+        "{\"client\":2,\"available\":\"100.0000\",\"held\":\"0.0000\",\"total\":\"100.0000\",\"locked\":false}\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_applies_money_policy_to_inputs_and_output() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+
+    let run = |arguments: &[&str]| -> Result<String> {
+        let mut child = Command::new(binary_path)
+            .args(arguments)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+            .write_all(b"type,client,tx,amount\ndeposit,1,1,1.005\ndeposit,1,2,2.5\n")?;
+
+        let output = child.wait_with_output()?;
+
+        assert!(output.status.success());
+
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    assert_eq!(run(&["--max-scale", "2", "--output-scale", "2"])?, "client,available,held,total,locked\n1,2.50,0.00,2.50,false\n");
+    assert_eq!(run(&["--max-scale", "2", "--excess-scale", "round", "--rounding", "half-up"])?, "client,available,held,total,locked\n1,3.5100,0.0000,3.5100,false\n");
+    assert_eq!(run(&["--rounding", "bankers", "--output-scale", "2"])?, "client,available,held,total,locked\n1,3.50,0.00,3.50,false\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_writes_one_row_per_client_and_currency() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+
+    let mut child = Command::new(binary_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+        .write_all(b"type,client,tx,amount,currency\ndeposit,2,1,3.0,\ndeposit,1,2,10.0,EUR\ndeposit,1,3,4.0,USD\ndispute,1,3,,\n")?;
+
+    let output = child.wait_with_output()?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, concat!(
+        "client,currency,available,held,total,locked\n",
+        "1,EUR,10.0000,0.0000,10.0000,false\n",
+        "1,USD,0.0000,4.0000,4.0000,false\n",
+        "2,,3.0000,0.0000,3.0000,false\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_transfers_funds_between_clients() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+
+    let mut child = Command::new(binary_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+        .write_all(b"type,client,tx,amount,to\ndeposit,1,1,10.0,\ntransfer,1,2,4.0,2\ntransfer,2,3,5.0,1\nwithdrawal,2,4,1.5,\n")?;
+
+    let output = child.wait_with_output()?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, concat!(
+        "client,available,held,total,locked\n",
+        "1,6.0000,0.0000,6.0000,false\n",
+        "2,2.5000,0.0000,2.5000,false\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_captures_and_releases_authorization_holds() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+
+    let mut child = Command::new(binary_path)
+        .arg("-")
+        .args(["--hold-expiry", "3600", "--now", "1700000000"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+        .write_all(b"type,client,tx,amount\ndeposit,1,1,10.0\nauthorize,1,2,4.0\nauthorize,1,3,1.5\ncapture,1,2,\nauthorize,2,4,1.0\ndeposit,2,5,2.0\nauthorize,2,6,0.5\nrelease,2,6,\n")?;
+
+    let output = child.wait_with_output()?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, concat!(
+        "client,available,held,total,locked\n",
+        "1,4.5000,1.5000,6.0000,false\n",
+        "2,2.0000,0.0000,2.0000,false\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_prints_audit_trail_of_one_client() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+    let fixture_path = Path::new("samples").join("fixed.csv");
+
+    let output = Command::new(binary_path)
+        .arg(&fixture_path)
+        .args(["--audit", "2"])
+        .output()?;
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let input = fixture_path.display();
+
+    assert_eq!(stdout, format!(
+        "input,line,client,tx,type,amount,currency,outcome,reason,available,held,total,locked\n\
+        {input},7,2,4,deposit,100.0000,,applied,,100.0000,0.0000,100.0000,false\n\
+        {input},8,2,5,withdrawal,200.0000,,rejected,E_INSUFFICIENT_FUNDS,100.0000,0.0000,100.0000,false\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_verifies_stored_history_and_reports_tampering() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+    let state = tempfile::tempdir()?;
+
+    let output = Command::new(binary_path)
+        .arg(Path::new("samples").join("fixed.csv"))
+        .arg("--state").arg(state.path())
+        .output()?;
+
+    assert!(output.status.success());
+
+    let output = Command::new(binary_path)
+        .args(["--verify", "--state"]).arg(state.path())
+        .output()?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, "Verified [2] accounts, every history is intact\n");
+
+    let snapshot = state.path().join("snapshot.jsonl");
+    std::fs::write(&snapshot, std::fs::read_to_string(&snapshot)?.replace(r#""tx":2,"amount":"20""#, r#""tx":2,"amount":"2000""#))?;
+
+    let output = Command::new(binary_path)
+        .args(["--verify", "--state"]).arg(state.path())
+        .output()?;
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("History of client [1] is broken at entry [1] for transaction [2]"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_reads_stdin_before_subsequent_files() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+    let fixture_path = Path::new("samples").join("fixed.csv");
+
+    let mut child = Command::new(binary_path)
+        .arg("-")
+        .arg(fixture_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+        .write_all(b"type,client,tx,amount\ndeposit,3,100,7.5\n")?;
+
+    let output = child.wait_with_output()?;
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(stdout.lines().any(|line| line == "3,7.5000,0.0000,7.5000,false"));
+    assert!(stdout.lines().any(|line| line == "1,25.0000,0.0000,25.0000,false"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_reads_json_lines_like_csv() -> Result<()> {
+    let binary_path = env!("CARGO_BIN_EXE_async-transaction-engine");
+
+    let csv = Command::new(binary_path)
+        .arg(Path::new("samples").join("fixed.csv"))
+        .output()?;
+
+    let jsonl = Command::new(binary_path)
+        .arg(Path::new("samples").join("fixed.jsonl"))
+        .output()?;
+
+    let sorted = |stdout: Vec<u8>| -> Result<Vec<String>> {
+        let mut lines: Vec<String> = String::from_utf8(stdout)?.lines().map(str::to_string).collect();
+        lines.sort();
+        Ok(lines)
+    };
+
+    assert!(jsonl.status.success());
+    assert_eq!(sorted(jsonl.stdout)?, sorted(csv.stdout)?);
+
+    let mut child = Command::new(binary_path)
+        .args(["--input-format", "jsonl", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child.stdin.take().ok_or_else(|| anyhow!("stdin unavailable"))?
+        .write_all(b"{\"type\":\"deposit\",\"client\":3,\"tx\":100,\"amount\":\"7.5\"}\n")?;
+
+    let output = child.wait_with_output()?;
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.lines().any(|line| line == "3,7.5000,0.0000,7.5000,false"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_library_engine_processes_fixture_in_process() -> Result<()> {
+    let storage = Arc::new(AccountStorage::new());
+    let engine = AsyncEngine::new(storage.clone());
+
+    engine.run("samples/fixed.csv").await?;
+
+    let client_1 = storage.load(1).ok_or_else(|| anyhow!("client 1 missing from storage"))?;
+
+    assert_eq!(client_1.available(), Decimal::from_str("25.0")?);
+    assert!(client_1.held().is_zero());
+    assert!(!client_1.is_locked());
+
+    Ok(())
+}
+
+#[test]
+fn test_library_account_applies_constructed_transactions() -> Result<()> {
+    let mut account = Account::new(7);
+
+    account.apply(&Transaction::deposit(7, 1, Decimal::from_str("10.0")?))?;
+    let result = account.apply(&Transaction::withdrawal(7, 2, Decimal::from_str("20.0")?));
+
+    assert!(matches!(result, Err(AccountError::InsufficientFunds { .. })));
+    assert_eq!(account.total(), Decimal::from_str("10.0")?);
+
+    Ok(())
+}